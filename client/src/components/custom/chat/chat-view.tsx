@@ -1,4 +1,4 @@
-import { useEvents, useMessages } from "@/hooks/api";
+import { useDefaultChannel, useEvents, useMessages } from "@/hooks/api";
 import MessageInput from "./message-input";
 import MessageView from "./message-view";
 import { useChatStore, useTypingStore } from "@/lib/store";
@@ -21,8 +21,10 @@ export default function ChatView() {
     const endRef = useRef<HTMLDivElement>(null);
     const messagesRef = useRef<HTMLDivElement>(null);
 
+    const channel = useDefaultChannel(groupStore.selectedGroup!!);
     const { data: messages, isLoading: isMessagesLoading } = useMessages(
         groupStore.selectedGroup!!,
+        channel?.id,
     );
     const { data: me, isLoading: isMeLoading } = useMe();
 
@@ -3,7 +3,7 @@ import { Input } from "../../ui/input";
 import { Form } from "@/components/ui/form";
 import { Messages } from "@/lib/api/message";
 import { useChatStore } from "@/lib/store";
-import { useSelectedGroup } from "@/hooks/api";
+import { useDefaultChannel, useSelectedGroup } from "@/hooks/api";
 import { useMutationWithErrorHandling } from "@/hooks/use-mutation";
 import { useErrorHandler } from "@/hooks/use-error-handler";
 import throttle from "lodash.throttle";
@@ -17,6 +17,7 @@ interface MessageInputData {
 export default function MessageInput() {
     const { selectedGroup: selectedGroupId } = useChatStore();
     const { data, isLoading } = useSelectedGroup();
+    const channel = useDefaultChannel(selectedGroupId!!);
 
     const form = useForm<MessageInputData>();
     const { handleSubmit, register, reset, setError, watch } = form;
@@ -43,13 +44,14 @@ export default function MessageInput() {
         return () => unsubscribe();
     }, [watch]);
 
-    if (isLoading) return;
+    if (isLoading || !channel) return;
 
     const onSubmit: SubmitHandler<MessageInputData> = async (data) => {
         reset();
 
         await mutateAsync({
-            groupId: selectedGroupId!!,
+            groupId: channel.groupId,
+            channelId: channel.id,
             content: data.content,
         });
     };
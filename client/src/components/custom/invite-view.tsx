@@ -1,24 +1,37 @@
 import { Trash, Users } from "lucide-react";
+import { useMutation, useQueryClient } from "@tanstack/react-query";
 import { Button } from "../ui/button";
 import { Card, CardContent } from "../ui/card";
-import { Invite } from "@/lib/api/invite";
+import { Invite, Invites } from "@/lib/api/invite";
 
 export interface InviteViewProps {
     invite: Invite;
 }
 
 export default function InviteView({ invite }: InviteViewProps) {
-    const formattedCode = `${invite.code.slice(0, 4)}-${invite.code.slice(4)}`;
+    const queryClient = useQueryClient();
+    const { mutate: deleteInvite } = useMutation({
+        mutationFn: () => Invites.delete(invite.groupId, invite.id),
+        onSuccess: () =>
+            queryClient.invalidateQueries({
+                queryKey: ["invites", invite.groupId],
+            }),
+    });
+
+    const uses =
+        invite.maxUses === null
+            ? `${invite.uses} uses`
+            : `${invite.uses} / ${invite.maxUses} uses`;
 
     return (
         <Card>
             <CardContent className="p-4">
                 <div className="flex items-center justify-between">
                     <div className="space-y-1">
-                        <p className="font-medium">{formattedCode}</p>
+                        <p className="font-medium">{invite.code}</p>
                         <div className="flex items-center gap-2 text-sm text-muted-foreground">
                             <Users className="h-4 w-4" />
-                            <span>{invite.uses} uses</span>
+                            <span>{uses}</span>
                         </div>
                     </div>
 
@@ -27,6 +40,7 @@ export default function InviteView({ invite }: InviteViewProps) {
                             variant="ghost"
                             size="icon"
                             className="text-destructive"
+                            onClick={() => deleteInvite()}
                         >
                             <Trash className="h-4 w-4" />
                         </Button>
@@ -18,6 +18,8 @@ import { useMutationWithErrorHandling } from "@/hooks/use-mutation";
 import { Turnstile } from "@marsidev/react-turnstile";
 import RootError from "./root-error";
 import { Auth } from "@/lib/api/auth";
+import MfaForm from "./mfa-form";
+import { useState } from "react";
 
 const loginSchema = z.object({
     username: z
@@ -34,6 +36,7 @@ type LoginData = z.infer<typeof loginSchema>;
 
 export default function LoginForm() {
     const navigate = useNavigate();
+    const [mfaRequired, setMfaRequired] = useState(false);
 
     const form = useForm<LoginData>({
         resolver: zodResolver(loginSchema),
@@ -64,6 +67,9 @@ export default function LoginForm() {
                 message: "The CAPTCHA verification failed.",
             },
         },
+        callbackMappings: {
+            [ErrorCode.MfaRequired]: () => setMfaRequired(true),
+        },
     });
 
     const { mutateAsync, isPending } = useMutationWithErrorHandling({
@@ -85,6 +91,10 @@ export default function LoginForm() {
         });
     };
 
+    if (mfaRequired) {
+        return <MfaForm />;
+    }
+
     return (
         <Form {...form}>
             <form onSubmit={handleSubmit(onSumbit)} className="space-y-4">
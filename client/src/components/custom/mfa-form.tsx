@@ -0,0 +1,101 @@
+import { zodResolver } from "@hookform/resolvers/zod";
+import { useNavigate } from "@tanstack/react-router";
+import { SubmitHandler, useForm } from "react-hook-form";
+import { z } from "zod";
+import {
+    Form,
+    FormControl,
+    FormField,
+    FormItem,
+    FormLabel,
+    FormMessage,
+} from "../ui/form";
+import { Input } from "../ui/input";
+import { Button } from "../ui/button";
+import { useErrorHandler } from "@/hooks/use-error-handler";
+import { ErrorCode } from "@/lib/api/error";
+import { useMutationWithErrorHandling } from "@/hooks/use-mutation";
+import RootError from "./root-error";
+import { Auth } from "@/lib/api/auth";
+
+const mfaSchema = z.object({
+    code: z.string().min(6).max(16),
+});
+
+type MfaData = z.infer<typeof mfaSchema>;
+
+export default function MfaForm() {
+    const navigate = useNavigate();
+
+    const form = useForm<MfaData>({
+        resolver: zodResolver(mfaSchema),
+        defaultValues: {
+            code: "",
+        },
+    });
+    const {
+        handleSubmit,
+        control,
+        setError,
+        formState: { errors },
+    } = form;
+
+    const handleError = useErrorHandler({
+        setError,
+        formMappings: {
+            [ErrorCode.InvalidMfaCode]: {
+                path: "code",
+                message: "Invalid code",
+            },
+            [ErrorCode.InvalidToken]: {
+                path: "root",
+                message: "Your login attempt expired, please reload the page",
+            },
+        },
+    });
+
+    const { mutateAsync, isPending } = useMutationWithErrorHandling({
+        onError: handleError,
+        mutationFn: Auth.loginTotp,
+
+        onSuccess: async () => {
+            await navigate({
+                to: "/",
+            });
+        },
+    });
+
+    const onSumbit: SubmitHandler<MfaData> = async (data) => {
+        await mutateAsync({ code: data.code });
+    };
+
+    return (
+        <Form {...form}>
+            <form onSubmit={handleSubmit(onSumbit)} className="space-y-4">
+                <RootError errors={errors} />
+
+                <FormField
+                    control={control}
+                    name="code"
+                    render={({ field }) => (
+                        <FormItem>
+                            <FormLabel>Authentication code</FormLabel>
+                            <FormControl>
+                                <Input
+                                    autoComplete="one-time-code"
+                                    placeholder="123456 or a recovery code"
+                                    {...field}
+                                />
+                            </FormControl>
+                            <FormMessage />
+                        </FormItem>
+                    )}
+                />
+
+                <Button className="w-full" type="submit" disabled={isPending}>
+                    {isPending ? "Verifying..." : "Verify"}
+                </Button>
+            </form>
+        </Form>
+    );
+}
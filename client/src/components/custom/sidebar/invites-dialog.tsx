@@ -22,7 +22,7 @@ export function InvitesDialog({ group, ...props }: InvitesDialogProps) {
 
     const { data: invites, isLoading } = useInvites(group.id);
     const { mutateAsync } = useMutation({
-        mutationFn: Invites.create,
+        mutationFn: (groupId: string) => Invites.create(groupId),
         onSuccess: (invite) =>
             queryClient.invalidateQueries({
                 queryKey: ["invites", invite.groupId],
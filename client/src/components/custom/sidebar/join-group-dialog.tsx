@@ -21,8 +21,7 @@ import { useMutation, useQueryClient } from "@tanstack/react-query";
 import { Invites } from "@/lib/api/invite";
 
 interface JoinGroupData {
-    left: string;
-    right: string;
+    code: string;
 }
 
 function JoinGroupForm({ onOpenChange }: DialogProps) {
@@ -38,15 +37,14 @@ function JoinGroupForm({ onOpenChange }: DialogProps) {
 
     const form = useForm<JoinGroupData>({
         defaultValues: {
-            left: "",
-            right: "",
+            code: "",
         },
     });
 
     const { handleSubmit, reset } = form;
 
     const onSubmit: SubmitHandler<JoinGroupData> = async (data) => {
-        const code = `${data.left}${data.right}`;
+        const code = data.code.trim();
         reset();
         onOpenChange?.(false);
 
@@ -56,57 +54,31 @@ function JoinGroupForm({ onOpenChange }: DialogProps) {
     return (
         <Form {...form}>
             <form onSubmit={handleSubmit(onSubmit)} className="space-y-4">
-                <div>
-                    <FormLabel className="text-sm">Invite Code</FormLabel>
-
-                    <div className="mt-1 flex items-center gap-2">
-                        <FormField
-                            control={form.control}
-                            name="left"
-                            render={({ field }) => (
-                                <FormItem>
-                                    <FormControl>
-                                        <Input
-                                            {...field}
-                                            maxLength={4}
-                                            className="text-center lowercase tracking-wider"
-                                            placeholder="abcd"
-                                            onChange={(e) =>
-                                                field.onChange(
-                                                    e.target.value.toLowerCase(),
-                                                )
-                                            }
-                                        />
-                                    </FormControl>
-                                    <FormMessage />
-                                </FormItem>
-                            )}
-                        />
-                        <span>-</span>
-                        <FormField
-                            control={form.control}
-                            name="right"
-                            render={({ field }) => (
-                                <FormItem>
-                                    <FormControl>
-                                        <Input
-                                            {...field}
-                                            maxLength={4}
-                                            className="text-center lowercase tracking-wider"
-                                            placeholder="efgh"
-                                            onChange={(e) =>
-                                                field.onChange(
-                                                    e.target.value.toLowerCase(),
-                                                )
-                                            }
-                                        />
-                                    </FormControl>
-                                    <FormMessage />
-                                </FormItem>
-                            )}
-                        />
-                    </div>
-                </div>
+                <FormField
+                    control={form.control}
+                    name="code"
+                    rules={{
+                        required: "Invite code is required",
+                        pattern: {
+                            value: /^[a-zA-Z0-9_-]{3,32}$/,
+                            message: "Invalid invite code",
+                        },
+                    }}
+                    render={({ field }) => (
+                        <FormItem>
+                            <FormLabel className="text-sm">Invite Code</FormLabel>
+                            <FormControl>
+                                <Input
+                                    {...field}
+                                    maxLength={32}
+                                    className="tracking-wider"
+                                    placeholder="aB3dE6gH9k"
+                                />
+                            </FormControl>
+                            <FormMessage />
+                        </FormItem>
+                    )}
+                />
 
                 <Button type="submit" className="w-full">
                     Join Group
@@ -1,3 +1,4 @@
+import { Channel, Channels } from "@/lib/api/channel";
 import { UpdatesEventSource } from "@/lib/api/event";
 import { Group, Groups } from "@/lib/api/group";
 import { Invite, Invites } from "@/lib/api/invite";
@@ -20,11 +21,30 @@ export function useSelectedGroup(): UseQueryResult<Group> {
     });
 }
 
-export function useMessages(id?: string): UseQueryResult<Message[]> {
+export function useChannels(groupId?: string): UseQueryResult<Channel[]> {
     return useQuery({
-        queryKey: ["messages", id],
-        queryFn: ({ queryKey: [_key, id] }) =>
-            id ? Messages.fetchAll(id, { limit: 50 }) : [],
+        queryKey: ["channels", groupId],
+        queryFn: ({ queryKey: [_key, groupId] }) =>
+            groupId ? Channels.fetchAll(groupId) : [],
+    });
+}
+
+/** The default channel of a group, which is the first one by position. */
+export function useDefaultChannel(groupId?: string): Channel | undefined {
+    const { data: channels } = useChannels(groupId);
+    return channels?.[0];
+}
+
+export function useMessages(
+    groupId?: string,
+    channelId?: string,
+): UseQueryResult<Message[]> {
+    return useQuery({
+        queryKey: ["messages", channelId],
+        queryFn: ({ queryKey: [_key, channelId] }) =>
+            groupId && channelId
+                ? Messages.fetchAll(groupId, channelId, { limit: 50 })
+                : [],
     });
 }
 
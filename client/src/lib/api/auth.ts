@@ -13,6 +13,30 @@ export interface RegisterRequest {
     token: string;
 }
 
+export interface MfaCodeRequest {
+    code: string;
+}
+
+export interface EnrollTotpResponse {
+    secret: string;
+    uri: string;
+}
+
+export interface RecoveryCodesResponse {
+    recoveryCodes: string[];
+}
+
+export interface Session {
+    readonly id: string;
+    readonly userId: string;
+    readonly userAgent: string | null;
+    readonly ipAddress: string | null;
+    readonly createdAt: string;
+    readonly lastSeenAt: string;
+    readonly expiresAt: string;
+    readonly current: boolean;
+}
+
 export class Auth {
     private static BASE_URL = "/auth";
 
@@ -30,6 +54,31 @@ export class Auth {
         return user;
     }
 
+    public static async loginTotp(request: MfaCodeRequest): Promise<User> {
+        const { data: user } = await instance.post<User>(`${Auth.BASE_URL}/login/totp`, request);
+
+        return user;
+    }
+
+    public static async enrollTotp(): Promise<EnrollTotpResponse> {
+        const { data } = await instance.post<EnrollTotpResponse>(`${Auth.BASE_URL}/totp`);
+
+        return data;
+    }
+
+    public static async confirmTotp(request: MfaCodeRequest): Promise<RecoveryCodesResponse> {
+        const { data } = await instance.post<RecoveryCodesResponse>(
+            `${Auth.BASE_URL}/totp/confirm`,
+            request,
+        );
+
+        return data;
+    }
+
+    public static async disableTotp(request: MfaCodeRequest): Promise<void> {
+        await instance.delete(`${Auth.BASE_URL}/totp`, { data: request });
+    }
+
     public static async register(request: RegisterRequest): Promise<User> {
         const { data: user } = await instance.post<User>(`${Auth.BASE_URL}/register`, request);
 
@@ -39,4 +88,24 @@ export class Auth {
     public static async logout(): Promise<void> {
         await instance.post(`${Auth.BASE_URL}/logout`)
     }
+
+    public static async logoutAll(): Promise<void> {
+        await instance.post(`${Auth.BASE_URL}/logout-all`);
+    }
+
+    public static async refresh(): Promise<User> {
+        const { data: user } = await instance.post<User>(`${Auth.BASE_URL}/refresh`);
+
+        return user;
+    }
+
+    public static async sessions(): Promise<Session[]> {
+        const { data } = await instance.get<Session[]>(`${Auth.BASE_URL}/sessions`);
+
+        return data;
+    }
+
+    public static async revokeSession(id: string): Promise<void> {
+        await instance.delete(`${Auth.BASE_URL}/sessions/${id}`);
+    }
 }
@@ -1,4 +1,5 @@
-import axios, { HttpStatusCode } from "axios";
+import axios, { AxiosError, HttpStatusCode, InternalAxiosRequestConfig } from "axios";
+import { ApiError, ErrorCode } from "./error";
 
 export const BASE_URL = import.meta.env.VITE_BASE_URL ?? "/api";
 
@@ -10,10 +11,48 @@ export const instance = axios.create({
     },
 });
 
+let refreshing: Promise<boolean> | null = null;
+
+export function refreshToken(): Promise<boolean> {
+    refreshing ??= instance
+        .post("/auth/refresh", undefined, { validateStatus: () => true })
+        .then((response) => response.status == HttpStatusCode.Ok)
+        .finally(() => (refreshing = null));
+
+    return refreshing;
+}
+
+type RetriableConfig = InternalAxiosRequestConfig & { retried?: boolean };
+
+instance.interceptors.response.use(undefined, async (error: AxiosError<ApiError>) => {
+    const config = error.config as RetriableConfig | undefined;
+
+    if (
+        !config ||
+        config.retried ||
+        config.url == "/auth/refresh" ||
+        error.response?.data?.code != ErrorCode.InvalidToken
+    ) {
+        throw error;
+    }
+
+    config.retried = true;
+    if (!(await refreshToken())) {
+        throw error;
+    }
+
+    return instance(config);
+});
+
 export async function verifyToken(): Promise<boolean> {
     const response = await instance.get("/auth/me", {
         validateStatus: () => true,
     });
 
-    return response.status == HttpStatusCode.Ok;
-}
\ No newline at end of file
+    if (response.status == HttpStatusCode.Ok) {
+        return true;
+    }
+
+    // The access token is short-lived, so a stale one only means it's time to refresh.
+    return await refreshToken();
+}
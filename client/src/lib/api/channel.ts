@@ -0,0 +1,68 @@
+import { instance } from "./axios";
+
+export interface Channel {
+    readonly id: string;
+    readonly groupId: string;
+    readonly name: string;
+    readonly topic?: string;
+    readonly position: number;
+    readonly createdAt: string;
+}
+
+export interface CreateChannelRequest {
+    name: string;
+    topic?: string;
+    position: number;
+}
+
+export type UpdateChannelRequest = Partial<CreateChannelRequest>;
+
+export class Channels {
+    private static getBasePath(groupId: string): string {
+        return `/groups/${groupId}/channels`;
+    }
+
+    static getUpdatesPath(groupId: string, channelId: string): string {
+        return `${Channels.getBasePath(groupId)}/${channelId}/updates`;
+    }
+
+    static async fetchAll(groupId: string): Promise<Channel[]> {
+        const { data } = await instance.get<Channel[]>(
+            Channels.getBasePath(groupId),
+        );
+        return data;
+    }
+
+    static async create(
+        groupId: string,
+        request: CreateChannelRequest,
+    ): Promise<Channel> {
+        const { data } = await instance.post<Channel>(
+            Channels.getBasePath(groupId),
+            request,
+        );
+        return data;
+    }
+
+    static async update(
+        groupId: string,
+        channelId: string,
+        request: UpdateChannelRequest,
+    ): Promise<Channel> {
+        const { data } = await instance.patch<Channel>(
+            `${Channels.getBasePath(groupId)}/${channelId}`,
+            request,
+        );
+        return data;
+    }
+
+    static async delete(groupId: string, channelId: string): Promise<void> {
+        await instance.delete(`${Channels.getBasePath(groupId)}/${channelId}`);
+    }
+
+    static async typing(groupId: string, channelId: string): Promise<void> {
+        await instance.post(
+            `${Channels.getBasePath(groupId)}/${channelId}/typing`,
+        );
+    }
+}
@@ -7,11 +7,43 @@ export enum ErrorCode {
     UnknownGroup = 5002,
     UserAlreadyExists = 5003,
     Validation = 5004,
+    AlreadyMember = 5005,
+    UnknownMessage = 5006,
+    InvalidOp = 5007,
+    UnknownSession = 5008,
+    MfaAlreadyEnabled = 5009,
+    MfaNotEnrolled = 5010,
+    UnknownRole = 5011,
+    UnknownMember = 5012,
+    RoleAlreadyExists = 5013,
+    DefaultRole = 5014,
+    OwnerCannotLeave = 5015,
+    UnknownBan = 5016,
+    InvalidImage = 5017,
+    InviteCodeTaken = 5018,
+    DirectMessageGroup = 5019,
+    CannotMessageSelf = 5020,
+    UnknownChannel = 5021,
+    ChannelAlreadyExists = 5022,
+    LastChannel = 5023,
+    InvalidThread = 5024,
+    TooManyAttachments = 5025,
+    AttachmentTooLarge = 5026,
+    UnsupportedAttachment = 5027,
+    UnknownAttachment = 5028,
+    InvalidMultipart = 5029,
+    UnknownFile = 5030,
+    ConflictingCursors = 5031,
+    EditWindowExpired = 5032,
+    TooManyReactions = 5033,
 
     InvalidToken = 6000,
     InsufficientPermissions = 6001,
     InvalidCredentials = 6002,
     CaptchaFailed = 6003,
+    MfaRequired = 6004,
+    InvalidMfaCode = 6005,
+    Banned = 6006,
 }
 
 export type Component = ["key", string] | ["index", number];
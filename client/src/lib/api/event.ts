@@ -4,6 +4,8 @@ import { BASE_URL } from "./axios";
 import { match } from "ts-pattern";
 import { User } from "./users";
 import { TypingStore } from "../store";
+import { Group, GroupMember, ReadState } from "./group";
+import { Channel } from "./channel";
 
 export const EVENT_SOURCE_NAME = "taqui";
 
@@ -11,25 +13,72 @@ export type BaseEvent<Name extends string, Data> = { event: Name; data: Data };
 
 export interface DeleteMessageEvent {
     groupId: string;
+    channelId: string;
     messageId: string;
 }
 
+export interface MentionEvent {
+    groupId: string;
+    channelId: string;
+    messageId: string;
+    authorId: string;
+    everyone: boolean;
+}
+
+export interface ReactionEvent {
+    groupId: string;
+    channelId: string;
+    messageId: string;
+    userId: string;
+    emoji: string;
+}
+
 export interface StartTypingEvent {
     groupId: string;
+    channelId: string;
     user: User;
 }
 
 export interface EndTypingEvent {
     groupId: string;
+    channelId: string;
     user: User;
 }
 
+export interface DeleteChannelEvent {
+    groupId: string;
+    channelId: string;
+}
+
+export interface AddMemberEvent {
+    groupId: string;
+    member: GroupMember;
+}
+
+export type RemovalReason = "leave" | "kick" | "ban";
+
+export interface RemoveMemberEvent {
+    groupId: string;
+    userId: string;
+    reason: RemovalReason;
+}
+
 export type Event =
     | BaseEvent<"newMessage", Message>
     | BaseEvent<"editMessage", Message>
     | BaseEvent<"deleteMessage", DeleteMessageEvent>
+    | BaseEvent<"mention", MentionEvent>
+    | BaseEvent<"reactionAdd", ReactionEvent>
+    | BaseEvent<"reactionRemove", ReactionEvent>
     | BaseEvent<"startTyping", StartTypingEvent>
-    | BaseEvent<"endTyping", EndTypingEvent>;
+    | BaseEvent<"endTyping", EndTypingEvent>
+    | BaseEvent<"readStateUpdate", ReadState>
+    | BaseEvent<"updateGroup", Group>
+    | BaseEvent<"addMember", AddMemberEvent>
+    | BaseEvent<"removeMember", RemoveMemberEvent>
+    | BaseEvent<"createChannel", Channel>
+    | BaseEvent<"updateChannel", Channel>
+    | BaseEvent<"deleteChannel", DeleteChannelEvent>;
 
 export class UpdatesEventSource extends EventSource {
     constructor(
@@ -55,36 +104,86 @@ export class UpdatesEventSource extends EventSource {
             .with({ event: "newMessage" }, ({ data }) =>
                 this.handleNewMessage(data),
             )
+            .with({ event: "mention" }, () => this.handleMention())
+            .with({ event: "reactionAdd" }, ({ data }) =>
+                this.handleReaction(data, 1),
+            )
+            .with({ event: "reactionRemove" }, ({ data }) =>
+                this.handleReaction(data, -1),
+            )
             .with({ event: "startTyping" }, ({ data }) => {
                 this.handleStartTyping(data);
             })
             .with({ event: "endTyping" }, ({ data }) => {
                 this.handleEndTyping(data);
-            }).exhaustive;
+            })
+            .with({ event: "readStateUpdate" }, () =>
+                this.handleReadStateUpdate(),
+            )
+            .with({ event: "updateGroup" }, ({ data }) =>
+                this.handleUpdateGroup(data),
+            )
+            .with({ event: "addMember" }, ({ data }) =>
+                this.handleAddMember(data),
+            )
+            .with({ event: "removeMember" }, ({ data }) =>
+                this.handleRemoveMember(data),
+            )
+            .with(
+                { event: "createChannel" },
+                { event: "updateChannel" },
+                ({ data }) => this.handleChannelChange(data.groupId),
+            )
+            .with({ event: "deleteChannel" }, ({ data }) =>
+                this.handleDeleteChannel(data),
+            ).exhaustive;
     }
 
     private handleNewMessage(message: Message) {
+        if (message.threadRoot) {
+            this.handleNewThreadMessage(message, message.threadRoot);
+            return;
+        }
+
         const messages =
             this.queryClient.getQueryData<Message[]>([
                 "messages",
-                message.groupId,
+                message.channelId,
             ]) ?? [];
 
         this.queryClient.setQueryData(
-            ["messages", message.groupId],
+            ["messages", message.channelId],
             [...messages, message],
         );
     }
 
+    private handleNewThreadMessage(message: Message, threadRoot: string) {
+        const messages =
+            this.queryClient.getQueryData<Message[]>([
+                "messages",
+                message.channelId,
+            ]) ?? [];
+
+        this.queryClient.setQueryData(
+            ["messages", message.channelId],
+            messages.map((root) =>
+                root.id == threadRoot
+                    ? { ...root, replyCount: root.replyCount + 1 }
+                    : root,
+            ),
+        );
+        this.queryClient.invalidateQueries({ queryKey: ["thread", threadRoot] });
+    }
+
     private handleEditMessage(newMessage: Message) {
         const messages =
             this.queryClient.getQueryData<Message[]>([
                 "messages",
-                newMessage.groupId,
+                newMessage.channelId,
             ]) ?? [];
 
         this.queryClient.setQueryData(
-            ["messages", newMessage.groupId],
+            ["messages", newMessage.channelId],
             messages.map((message) =>
                 message.id == newMessage.id ? newMessage : message,
             ),
@@ -95,15 +194,49 @@ export class UpdatesEventSource extends EventSource {
         const messages =
             this.queryClient.getQueryData<Message[]>([
                 "messages",
-                event.groupId,
+                event.channelId,
             ]) ?? [];
 
         this.queryClient.setQueryData(
-            ["messages", event.groupId],
+            ["messages", event.channelId],
             messages.filter((message) => message.id != event.messageId),
         );
     }
 
+    private handleMention() {
+        this.queryClient.invalidateQueries({ queryKey: ["groups"], exact: true });
+    }
+
+    private handleReaction(event: ReactionEvent, delta: number) {
+        const update = (message: Message): Message => {
+            if (message.id != event.messageId) return message;
+
+            const reactions = message.reactions.some(
+                (reaction) => reaction.emoji == event.emoji,
+            )
+                ? message.reactions.map((reaction) =>
+                      reaction.emoji == event.emoji
+                          ? { ...reaction, count: reaction.count + delta }
+                          : reaction,
+                  )
+                : [...message.reactions, { emoji: event.emoji, count: delta }];
+
+            return {
+                ...message,
+                reactions: reactions.filter((reaction) => reaction.count > 0),
+            };
+        };
+
+        this.queryClient.setQueriesData<Message[]>(
+            { queryKey: ["messages", event.channelId] },
+            (messages) => messages?.map(update),
+        );
+        this.queryClient.setQueriesData<Message[]>(
+            { queryKey: ["thread"] },
+            (messages) => messages?.map(update),
+        );
+    }
+
     private handleStartTyping(event: StartTypingEvent) {
         this.typing.add(event.user);
     }
@@ -111,4 +244,35 @@ export class UpdatesEventSource extends EventSource {
     private handleEndTyping(event: EndTypingEvent) {
         this.typing.remove(event.user);
     }
+
+    private handleReadStateUpdate() {
+        this.queryClient.invalidateQueries({ queryKey: ["groups"], exact: true });
+    }
+
+    private handleUpdateGroup(group: Group) {
+        this.queryClient.setQueryData(["groups", group.id], group);
+        this.queryClient.invalidateQueries({ queryKey: ["groups"], exact: true });
+    }
+
+    private handleAddMember(event: AddMemberEvent) {
+        this.queryClient.invalidateQueries({
+            queryKey: ["members", event.groupId],
+        });
+    }
+
+    private handleRemoveMember(event: RemoveMemberEvent) {
+        this.queryClient.invalidateQueries({
+            queryKey: ["members", event.groupId],
+        });
+        this.queryClient.invalidateQueries({ queryKey: ["groups"] });
+    }
+
+    private handleChannelChange(groupId: string) {
+        this.queryClient.invalidateQueries({ queryKey: ["channels", groupId] });
+    }
+
+    private handleDeleteChannel(event: DeleteChannelEvent) {
+        this.queryClient.removeQueries({ queryKey: ["messages", event.channelId] });
+        this.handleChannelChange(event.groupId);
+    }
 }
@@ -1,16 +1,69 @@
-import { instance } from "./axios";
+import { BASE_URL, instance } from "./axios";
 import { User } from "./users";
 
 export interface Member {
     readonly userId: string;
     readonly groupId: string;
     readonly joinedAt: string;
+    readonly roleId: string;
+}
+
+export interface GroupMember extends User {
+    readonly roleId: string;
+    readonly joinedAt: string;
+}
+
+export interface UpdateMemberRequest {
+    roleId: string;
+}
+
+export interface Ban {
+    readonly groupId: string;
+    readonly userId: string;
+    readonly bannedBy: string | null;
+    readonly reason: string | null;
+    readonly createdAt: string;
+}
+
+export interface BanMemberRequest {
+    reason?: string;
+}
+
+export interface TransferGroupRequest {
+    userId: string;
 }
 
 export interface Group {
     readonly id: string;
     readonly ownerId: string;
     readonly name: string;
+    readonly description: string | null;
+    readonly icon: string | null;
+    readonly isDirect: boolean;
+    /** Seconds after sending during which messages can be edited. */
+    readonly editWindow: number | null;
+    /** Only included when listing the groups of the user. */
+    readonly unreadCount?: number;
+    readonly mentionCount?: number;
+    readonly readStates?: ReadState[];
+}
+
+export interface ReadState {
+    readonly groupId: string;
+    readonly channelId: string;
+    readonly lastReadMessageId: string | null;
+    readonly unreadCount: number;
+    readonly mentionCount: number;
+}
+
+export interface UpdateGroupRequest {
+    name?: string;
+    /** An empty string removes the description. */
+    description?: string;
+    /** Base64-encoded image; an empty string removes the icon. */
+    icon?: string;
+    /** Zero removes the limit. */
+    editWindow?: number;
 }
 
 export interface CreateGroupRequest {
@@ -24,13 +77,60 @@ export class Groups {
         return `${Groups.BASE_PATH}/${id}/updates`;
     }
 
-    static async fetchMembers(id: string): Promise<User[]> {
-        const { data } = await instance.get<User[]>(
+    static getIconUrl(group: Group): string | null {
+        return group.icon ? `${BASE_URL}/files/${group.icon}` : null;
+    }
+
+    static async fetchMembers(id: string): Promise<GroupMember[]> {
+        const { data } = await instance.get<GroupMember[]>(
             `${Groups.BASE_PATH}/${id}/members`,
         );
         return data;
     }
 
+    static async updateMember(
+        groupId: string,
+        userId: string,
+        request: UpdateMemberRequest,
+    ): Promise<Member> {
+        const { data } = await instance.patch<Member>(
+            `${Groups.BASE_PATH}/${groupId}/members/${userId}`,
+            request,
+        );
+        return data;
+    }
+
+    static async removeMember(groupId: string, userId: string): Promise<void> {
+        await instance.delete(`${Groups.BASE_PATH}/${groupId}/members/${userId}`);
+    }
+
+    static async leave(groupId: string, selfId: string): Promise<void> {
+        await Groups.removeMember(groupId, selfId);
+    }
+
+    static async banMember(
+        groupId: string,
+        userId: string,
+        request: BanMemberRequest = {},
+    ): Promise<Ban> {
+        const { data } = await instance.post<Ban>(
+            `${Groups.BASE_PATH}/${groupId}/members/${userId}/ban`,
+            request,
+        );
+        return data;
+    }
+
+    static async fetchBans(groupId: string): Promise<Ban[]> {
+        const { data } = await instance.get<Ban[]>(
+            `${Groups.BASE_PATH}/${groupId}/bans`,
+        );
+        return data;
+    }
+
+    static async unban(groupId: string, userId: string): Promise<void> {
+        await instance.delete(`${Groups.BASE_PATH}/${groupId}/bans/${userId}`);
+    }
+
     static async fetchAll(): Promise<Group[]> {
         const { data } = await instance.get<Group[]>(Groups.BASE_PATH);
         return data;
@@ -46,6 +146,28 @@ export class Groups {
         return data;
     }
 
+    static async update(
+        id: string,
+        request: UpdateGroupRequest,
+    ): Promise<Group> {
+        const { data } = await instance.patch<Group>(
+            `${Groups.BASE_PATH}/${id}`,
+            request,
+        );
+        return data;
+    }
+
+    static async transfer(
+        groupId: string,
+        request: TransferGroupRequest,
+    ): Promise<Group> {
+        const { data } = await instance.post<Group>(
+            `${Groups.BASE_PATH}/${groupId}/transfer`,
+            request,
+        );
+        return data;
+    }
+
     static async typing(groupId: string): Promise<void> {
         await instance.post(`${Groups.BASE_PATH}/${groupId}/typing`);
     }
@@ -1,13 +1,31 @@
 import { instance } from "./axios";
+import { Group } from "./group";
 
 export interface Invite {
     readonly id: string;
     readonly code: string;
     readonly uses: number;
+    readonly maxUses: number | null;
 
     readonly userId: string;
     readonly groupId: string;
     readonly createdAt: string;
+    readonly expiresAt: string | null;
+}
+
+export interface CreateInviteRequest {
+    /** Vanity code, which only the group owner can set. */
+    code?: string;
+    maxUses?: number;
+    /** Seconds until the invite expires. */
+    maxAge?: number;
+}
+
+export interface InvitePreview {
+    readonly code: string;
+    readonly expiresAt: string | null;
+    readonly group: Group;
+    readonly memberCount: number;
 }
 
 export class Invites {
@@ -25,9 +43,27 @@ export class Invites {
         return data;
     }
 
-    public static async create(groupId: string): Promise<Invite> {
+    public static async create(
+        groupId: string,
+        request: CreateInviteRequest = {},
+    ): Promise<Invite> {
         const { data } = await instance.post<Invite>(
             Invites.getGroupBasePath(groupId),
+            request,
+        );
+
+        return data;
+    }
+
+    public static async delete(groupId: string, inviteId: string): Promise<void> {
+        await instance.delete(
+            `${Invites.getGroupBasePath(groupId)}/${inviteId}`,
+        );
+    }
+
+    public static async preview(code: string): Promise<InvitePreview> {
+        const { data } = await instance.get<InvitePreview>(
+            `${Invites.basePath}/${code}`,
         );
 
         return data;
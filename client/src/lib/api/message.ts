@@ -1,18 +1,75 @@
-import { instance } from "./axios";
+import { BASE_URL, instance } from "./axios";
+import { ReadState } from "./group";
+
+export interface ReactionCount {
+    readonly emoji: string;
+    readonly count: number;
+}
+
+export interface Attachment {
+    readonly id: string;
+    readonly filename: string;
+    readonly contentType: string;
+    readonly size: number;
+}
+
+export interface MentionedUser {
+    readonly id: string;
+    readonly username: string;
+}
 
 export interface Message {
     readonly id: string;
     readonly userId: string;
     readonly groupId: string;
+    readonly channelId: string;
     readonly content: string;
 
+    readonly replyTo?: string;
+    readonly threadRoot?: string;
+    readonly replyCount: number;
+    readonly reactions: ReactionCount[];
+    readonly attachments: Attachment[];
+    readonly mentions: MentionedUser[];
+    readonly mentionsEveryone: boolean;
+
     readonly createdAt: string;
     readonly updatedAt?: string;
 }
 
+export interface MessageRevision {
+    readonly id: string;
+    readonly messageId: string;
+    readonly content: string;
+    readonly createdAt: string;
+    readonly replacedAt: string;
+}
+
 export interface FetchMessagesParams {
     limit: number;
+    /** Only one of the cursors below can be set. */
     before?: string;
+    after?: string;
+    around?: string;
+    since?: Date;
+}
+
+export interface SearchMessagesParams {
+    q: string;
+    channelId?: string;
+    authorId?: string;
+    after?: Date;
+    before?: Date;
+    hasLink?: boolean;
+    limit?: number;
+    offset?: number;
+}
+
+export interface SearchResult {
+    readonly message: Message;
+    readonly rank: number;
+    /** HTML-escaped content with the matching words wrapped in `<mark>` tags. */
+    readonly highlight: string;
 }
 
 export interface DeleteMessageRequest {
@@ -22,7 +79,11 @@ export interface DeleteMessageRequest {
 
 export interface CreateMessageRequest {
     groupId: string;
+    channelId: string;
     content: string;
+    replyTo?: string;
+    threadRoot?: string;
+    files?: File[];
 }
 
 export interface EditMessageRequest {
@@ -36,16 +97,21 @@ export class Messages {
         return `/groups/${groupId}/messages`;
     }
 
+    private static getChannelPath(groupId: string, channelId: string): string {
+        return `/groups/${groupId}/channels/${channelId}/messages`;
+    }
+
     static async fetchAll(
         groupId: string,
+        channelId: string,
         params: FetchMessagesParams,
     ): Promise<Message[]> {
         const { data } = await instance.get<Message[]>(
-            `${Messages.getBasePath(groupId)}`,
+            `${Messages.getChannelPath(groupId, channelId)}`,
             {
                 params: {
-                    before: params?.before,
-                    limit: params.limit,
+                    ...params,
+                    since: params.since?.toISOString(),
                 },
             },
         );
@@ -53,15 +119,69 @@ export class Messages {
         return data;
     }
 
-    static async create(request: CreateMessageRequest): Promise<Message> {
-        const { data } = await instance.post<Message>(
-            `${Messages.getBasePath(request.groupId)}`,
-            request,
+    static async fetchThread(
+        groupId: string,
+        messageId: string,
+        params: FetchMessagesParams,
+    ): Promise<Message[]> {
+        const { data } = await instance.get<Message[]>(
+            `${Messages.getBasePath(groupId)}/${messageId}/thread`,
+            {
+                params: {
+                    ...params,
+                    since: params.since?.toISOString(),
+                },
+            },
         );
 
         return data;
     }
 
+    /** Searches a group, or every group of the user when no group is given. */
+    static async search(
+        params: SearchMessagesParams,
+        groupId?: string,
+    ): Promise<SearchResult[]> {
+        const { channelId, ...query } = params;
+        let path = "/messages/search";
+        if (groupId) {
+            path = channelId
+                ? `${Messages.getChannelPath(groupId, channelId)}/search`
+                : `${Messages.getBasePath(groupId)}/search`;
+        }
+
+        const { data } = await instance.get<SearchResult[]>(path, {
+            params: {
+                ...query,
+                after: query.after?.toISOString(),
+                before: query.before?.toISOString(),
+            },
+        });
+
+        return data;
+    }
+
+    static getAttachmentUrl(groupId: string, attachment: Attachment): string {
+        return `${BASE_URL}/groups/${groupId}/attachments/${attachment.id}`;
+    }
+
+    static async create(request: CreateMessageRequest): Promise<Message> {
+        const { files, ...payload } = request;
+        const path = Messages.getChannelPath(request.groupId, request.channelId);
+
+        if (!files?.length) {
+            const { data } = await instance.post<Message>(path, payload);
+            return data;
+        }
+
+        const form = new FormData();
+        form.append("payload_json", JSON.stringify(payload));
+        files.forEach((file) => form.append("files", file));
+
+        const { data } = await instance.post<Message>(path, form);
+        return data;
+    }
+
     static async edit(request: EditMessageRequest): Promise<Message> {
         const { data } = await instance.patch<Message>(
             `${Messages.getBasePath(request.groupId)}/${request.messageId}`,
@@ -71,6 +191,48 @@ export class Messages {
         return data;
     }
 
+    static async addReaction(
+        groupId: string,
+        messageId: string,
+        emoji: string,
+    ): Promise<void> {
+        await instance.put(
+            `${Messages.getBasePath(groupId)}/${messageId}/reactions/${encodeURIComponent(emoji)}`,
+        );
+    }
+
+    static async removeReaction(
+        groupId: string,
+        messageId: string,
+        emoji: string,
+    ): Promise<void> {
+        await instance.delete(
+            `${Messages.getBasePath(groupId)}/${messageId}/reactions/${encodeURIComponent(emoji)}`,
+        );
+    }
+
+    static async fetchHistory(
+        groupId: string,
+        messageId: string,
+    ): Promise<MessageRevision[]> {
+        const { data } = await instance.get<MessageRevision[]>(
+            `${Messages.getBasePath(groupId)}/${messageId}/history`,
+        );
+
+        return data;
+    }
+
+    static async ack(
+        groupId: string,
+        messageId: string,
+    ): Promise<ReadState> {
+        const { data } = await instance.post<ReadState>(
+            `${Messages.getBasePath(groupId)}/${messageId}/ack`,
+        );
+
+        return data;
+    }
+
     static async delete(request: DeleteMessageRequest): Promise<void> {
         await instance.delete(`${Messages.getBasePath(request.groupId)}/${request.messageId}`);
     }
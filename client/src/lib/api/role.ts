@@ -0,0 +1,68 @@
+import { instance } from "./axios";
+
+export enum Permission {
+    SendMessages = 1 << 0,
+    CreateInvites = 1 << 1,
+    ManageInvites = 1 << 2,
+    ManageMessages = 1 << 3,
+    KickMembers = 1 << 4,
+    BanMembers = 1 << 5,
+    ManageRoles = 1 << 6,
+    ManageGroup = 1 << 7,
+    DeleteGroup = 1 << 8,
+    MentionEveryone = 1 << 9,
+}
+
+export interface Role {
+    readonly id: string;
+    readonly groupId: string;
+    readonly name: string;
+    readonly permissions: number;
+    readonly position: number;
+    readonly isDefault: boolean;
+    readonly createdAt: string;
+}
+
+export interface CreateRoleRequest {
+    name: string;
+    permissions: number;
+    position: number;
+}
+
+export type UpdateRoleRequest = Partial<CreateRoleRequest>;
+
+export function hasPermission(role: Role, permission: Permission): boolean {
+    return (role.permissions & permission) == permission;
+}
+
+export class Roles {
+    private static getBasePath(groupId: string): string {
+        return `/groups/${groupId}/roles`;
+    }
+
+    static async fetchAll(groupId: string): Promise<Role[]> {
+        const { data } = await instance.get<Role[]>(Roles.getBasePath(groupId));
+        return data;
+    }
+
+    static async create(groupId: string, request: CreateRoleRequest): Promise<Role> {
+        const { data } = await instance.post<Role>(Roles.getBasePath(groupId), request);
+        return data;
+    }
+
+    static async update(
+        groupId: string,
+        roleId: string,
+        request: UpdateRoleRequest,
+    ): Promise<Role> {
+        const { data } = await instance.patch<Role>(
+            `${Roles.getBasePath(groupId)}/${roleId}`,
+            request,
+        );
+        return data;
+    }
+
+    static async delete(groupId: string, roleId: string): Promise<void> {
+        await instance.delete(`${Roles.getBasePath(groupId)}/${roleId}`);
+    }
+}
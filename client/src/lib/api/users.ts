@@ -1,4 +1,5 @@
 import { instance } from "./axios";
+import { Group } from "./group";
 
 export interface User {
     readonly id: string,
@@ -6,6 +7,19 @@ export interface User {
     readonly createdAt: string
 }
 
+export interface UpdatePasswordRequest {
+    oldPassword: string;
+    newPassword: string;
+}
+
+export interface DeleteUserRequest {
+    password: string;
+}
+
+export interface DirectMessage extends Group {
+    readonly recipient: User;
+}
+
 export class Users {
     private static readonly BASE_PATH = "/users";
 
@@ -13,4 +27,23 @@ export class Users {
         const { data } = await instance.get(`${Users.BASE_PATH}/${id}`);
         return data;
     }
+
+    static async fetchDirectMessages(): Promise<DirectMessage[]> {
+        const { data } = await instance.get(`${Users.BASE_PATH}/@me/dms`);
+        return data;
+    }
+
+    static async openDirectMessage(id: string): Promise<DirectMessage> {
+        const { data } = await instance.post(`${Users.BASE_PATH}/${id}/dm`);
+        return data;
+    }
+
+    static async updatePassword(request: UpdatePasswordRequest): Promise<User> {
+        const { data } = await instance.patch(`${Users.BASE_PATH}/@me/password`, request);
+        return data;
+    }
+
+    static async deleteSelf(request: DeleteUserRequest): Promise<void> {
+        await instance.delete(`${Users.BASE_PATH}/@me`, { data: request });
+    }
 }  
\ No newline at end of file
@@ -0,0 +1,23 @@
+CREATE SEQUENCE event_ids;
+
+CREATE FUNCTION publish_event(subscription text, event text)
+        RETURNS bigint
+        LANGUAGE plpgsql
+    AS
+$$
+DECLARE
+    id bigint;
+BEGIN
+    -- Publishers are serialized so ids are handed out in commit order,
+    -- which is the order NOTIFY delivers them to every listener.
+    PERFORM pg_advisory_xact_lock(hashtext('publish_event'));
+
+    id = nextval('event_ids');
+    PERFORM pg_notify(
+        'taqui_events',
+        json_build_object('id', id, 'subscription', subscription::json, 'event', event::json)::text
+    );
+
+    RETURN id;
+END;
+$$;
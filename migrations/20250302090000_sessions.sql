@@ -0,0 +1,12 @@
+CREATE TABLE sessions (
+  id uuid NOT NULL PRIMARY KEY DEFAULT (gen_random_uuid()),
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  refresh_token_hash text NOT NULL UNIQUE,
+  user_agent text,
+  ip_address text,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+  last_seen_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+  expires_at timestamp NOT NULL
+);
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
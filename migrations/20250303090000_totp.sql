@@ -0,0 +1,14 @@
+CREATE TABLE totp (
+  user_id uuid NOT NULL PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  secret text NOT NULL,
+  enabled boolean NOT NULL DEFAULT false,
+  last_used_step bigint,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+
+CREATE TABLE recovery_codes (
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  code_hash text NOT NULL,
+
+  PRIMARY KEY(user_id, code_hash)
+);
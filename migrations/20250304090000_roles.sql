@@ -0,0 +1,27 @@
+CREATE TABLE roles (
+  id uuid NOT NULL PRIMARY KEY DEFAULT (gen_random_uuid()),
+  group_id uuid NOT NULL REFERENCES groups (id) ON DELETE CASCADE,
+  name varchar NOT NULL,
+  permissions bigint NOT NULL DEFAULT 0,
+  position integer NOT NULL DEFAULT 0,
+  is_default boolean NOT NULL DEFAULT false,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+
+  UNIQUE(group_id, name)
+);
+
+CREATE UNIQUE INDEX roles_default_idx ON roles (group_id) WHERE is_default;
+
+-- Built-in roles for existing groups, mirroring `Role::BUILT_IN`.
+INSERT INTO roles (group_id, name, permissions, position, is_default)
+  SELECT id, 'member', 1, 0, true FROM groups
+  UNION ALL SELECT id, 'moderator', 31, 1, false FROM groups
+  UNION ALL SELECT id, 'admin', 255, 2, false FROM groups;
+
+ALTER TABLE members ADD COLUMN role_id uuid REFERENCES roles (id);
+
+UPDATE members SET role_id = roles.id
+  FROM roles
+  WHERE roles.group_id = members.group_id AND roles.is_default;
+
+ALTER TABLE members ALTER COLUMN role_id SET NOT NULL;
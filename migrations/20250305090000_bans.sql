@@ -0,0 +1,9 @@
+CREATE TABLE bans (
+  group_id uuid NOT NULL REFERENCES groups (id) ON DELETE CASCADE,
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  banned_by uuid REFERENCES users (id) ON DELETE SET NULL,
+  reason text,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+
+  PRIMARY KEY(group_id, user_id)
+);
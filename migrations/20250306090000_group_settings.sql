@@ -0,0 +1,3 @@
+ALTER TABLE groups
+  ADD COLUMN description text,
+  ADD COLUMN icon text;
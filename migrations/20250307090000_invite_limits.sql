@@ -0,0 +1,3 @@
+ALTER TABLE invites
+  ADD COLUMN max_uses integer CHECK (max_uses > 0),
+  ADD COLUMN expires_at timestamp;
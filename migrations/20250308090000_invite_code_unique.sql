@@ -0,0 +1,11 @@
+ALTER TABLE invites ALTER COLUMN code DROP DEFAULT;
+DROP FUNCTION gen_random_invite_code();
+
+UPDATE invites SET code = substr(md5(random()::text || id::text), 1, 10)
+  WHERE id IN (
+    SELECT id FROM (
+      SELECT id, row_number() OVER (PARTITION BY code ORDER BY created_at) AS n FROM invites
+    ) codes WHERE n > 1
+  );
+
+CREATE UNIQUE INDEX invites_code_key ON invites (code);
@@ -0,0 +1,12 @@
+ALTER TABLE groups ADD COLUMN is_direct boolean NOT NULL DEFAULT false;
+
+CREATE TABLE direct_messages (
+  group_id uuid NOT NULL PRIMARY KEY REFERENCES groups (id) ON DELETE CASCADE,
+  first_user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  second_user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+  CHECK (first_user_id < second_user_id),
+  UNIQUE (first_user_id, second_user_id)
+);
+
+CREATE INDEX direct_messages_second_user_id_idx ON direct_messages (second_user_id);
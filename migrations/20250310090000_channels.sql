@@ -0,0 +1,21 @@
+CREATE TABLE channels (
+  id uuid NOT NULL PRIMARY KEY DEFAULT (gen_random_uuid()),
+  group_id uuid NOT NULL REFERENCES groups (id) ON DELETE CASCADE,
+  name varchar NOT NULL,
+  topic text,
+  position integer NOT NULL DEFAULT 0,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+
+  UNIQUE(group_id, name)
+);
+
+INSERT INTO channels (group_id, name) SELECT id, 'general' FROM groups;
+
+ALTER TABLE messages ADD COLUMN channel_id uuid REFERENCES channels (id) ON DELETE CASCADE;
+
+UPDATE messages SET channel_id = channels.id
+  FROM channels WHERE channels.group_id = messages.group_id;
+
+ALTER TABLE messages ALTER COLUMN channel_id SET NOT NULL;
+
+CREATE INDEX messages_channel_id_created_at_idx ON messages (channel_id, created_at);
@@ -0,0 +1,6 @@
+ALTER TABLE messages
+  ADD COLUMN reply_to uuid REFERENCES messages (id) ON DELETE SET NULL,
+  ADD COLUMN thread_root uuid REFERENCES messages (id) ON DELETE CASCADE;
+
+CREATE INDEX messages_thread_root_created_at_idx ON messages (thread_root, created_at)
+  WHERE thread_root IS NOT NULL;
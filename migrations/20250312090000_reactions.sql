@@ -0,0 +1,8 @@
+CREATE TABLE reactions (
+  message_id uuid NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  emoji varchar NOT NULL,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+
+  PRIMARY KEY (message_id, user_id, emoji)
+);
@@ -0,0 +1,31 @@
+CREATE TABLE attachments (
+  id uuid NOT NULL PRIMARY KEY,
+  message_id uuid NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+  key varchar NOT NULL,
+  filename varchar NOT NULL,
+  content_type varchar NOT NULL,
+  size integer NOT NULL,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+
+CREATE INDEX attachments_message_id_idx ON attachments (message_id);
+
+-- Attachments go away along with their message, channel, group or author, so
+-- their files are queued here and removed from storage in the background.
+CREATE TABLE orphaned_files (
+  key varchar NOT NULL PRIMARY KEY,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+
+CREATE OR REPLACE FUNCTION queue_attachment_file_deletion()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO orphaned_files (key) VALUES (OLD.key) ON CONFLICT DO NOTHING;
+    RETURN OLD;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER queue_attachment_file_deletion
+AFTER DELETE ON attachments
+FOR EACH ROW
+EXECUTE FUNCTION queue_attachment_file_deletion();
@@ -0,0 +1,5 @@
+ALTER TABLE messages
+  ADD COLUMN search_vector tsvector NOT NULL
+    GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+
+CREATE INDEX messages_search_vector_idx ON messages USING GIN (search_vector);
@@ -0,0 +1,6 @@
+DROP INDEX messages_channel_id_created_at_idx;
+DROP INDEX messages_thread_root_created_at_idx;
+
+CREATE INDEX messages_channel_id_created_at_id_idx ON messages (channel_id, created_at, id);
+CREATE INDEX messages_thread_root_created_at_id_idx ON messages (thread_root, created_at, id)
+  WHERE thread_root IS NOT NULL;
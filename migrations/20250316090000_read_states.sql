@@ -0,0 +1,12 @@
+CREATE TABLE read_states (
+  user_id uuid NOT NULL,
+  group_id uuid NOT NULL,
+  channel_id uuid NOT NULL REFERENCES channels (id) ON DELETE CASCADE,
+  -- Not a foreign key so that the position survives the message being deleted.
+  last_read_message_id uuid NOT NULL,
+  last_read_at timestamp NOT NULL,
+  updated_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+
+  PRIMARY KEY (user_id, channel_id),
+  FOREIGN KEY (user_id, group_id) REFERENCES members (user_id, group_id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+ALTER TABLE messages ADD COLUMN mentions_everyone boolean NOT NULL DEFAULT false;
+
+CREATE TABLE mentions (
+  message_id uuid NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+  user_id uuid NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+  PRIMARY KEY (message_id, user_id)
+);
+
+CREATE INDEX mentions_user_id_idx ON mentions (user_id);
+
+-- `Permission::MENTION_EVERYONE`, given to the roles trusted to manage messages.
+UPDATE roles SET permissions = permissions | 512 WHERE permissions & 8 <> 0;
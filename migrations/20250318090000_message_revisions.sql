@@ -0,0 +1,28 @@
+-- Seconds after sending during which messages can be edited, unlimited when NULL.
+ALTER TABLE groups ADD COLUMN edit_window integer;
+
+CREATE TABLE message_revisions (
+  id uuid NOT NULL PRIMARY KEY DEFAULT (gen_random_uuid()),
+  message_id uuid NOT NULL REFERENCES messages (id) ON DELETE CASCADE,
+  content varchar NOT NULL,
+  -- When this content was written, either by sending or by a previous edit.
+  created_at timestamp NOT NULL,
+  replaced_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+
+CREATE INDEX message_revisions_message_id_idx ON message_revisions (message_id, created_at);
+
+CREATE OR REPLACE FUNCTION record_message_revision()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO message_revisions (message_id, content, created_at)
+      VALUES (OLD.id, OLD.content, COALESCE(OLD.updated_at, OLD.created_at));
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER record_message_revision
+AFTER UPDATE OF content ON messages
+FOR EACH ROW
+WHEN (OLD.content IS DISTINCT FROM NEW.content)
+EXECUTE FUNCTION record_message_revision();
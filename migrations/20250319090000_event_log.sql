@@ -0,0 +1,31 @@
+-- Events are kept for a short while so listeners can read them by id, which
+-- lifts the NOTIFY payload limit and lets publishers run concurrently.
+CREATE TABLE events (
+  id bigint NOT NULL PRIMARY KEY DEFAULT nextval('event_ids'),
+  subscription text NOT NULL,
+  event text NOT NULL,
+  created_at timestamp NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
+
+ALTER SEQUENCE event_ids OWNED BY events.id;
+
+CREATE INDEX events_created_at_idx ON events (created_at);
+
+CREATE OR REPLACE FUNCTION publish_event(subscription text, event text)
+        RETURNS bigint
+        LANGUAGE plpgsql
+    AS
+$$
+DECLARE
+    id bigint;
+BEGIN
+    -- Ids may commit out of order; listeners put them back in order on read.
+    INSERT INTO events (subscription, event)
+      VALUES (publish_event.subscription, publish_event.event)
+      RETURNING events.id INTO id;
+
+    PERFORM pg_notify('taqui_events', id::text);
+
+    RETURN id;
+END;
+$$;
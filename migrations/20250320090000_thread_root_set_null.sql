@@ -0,0 +1,6 @@
+-- Deleting a thread root used to take every reply with it, bypassing the
+-- permission check and the delete events. Replies now stay in the channel.
+ALTER TABLE messages
+  DROP CONSTRAINT messages_thread_root_fkey,
+  ADD CONSTRAINT messages_thread_root_fkey
+    FOREIGN KEY (thread_root) REFERENCES messages (id) ON DELETE SET NULL;
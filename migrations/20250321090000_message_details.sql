@@ -0,0 +1,24 @@
+-- Messages along with the thread, reaction, attachment and mention details
+-- every listing of them includes.
+CREATE VIEW message_details AS
+SELECT id, user_id, group_id, channel_id, content, reply_to, thread_root, mentions_everyone, created_at, updated_at,
+  (SELECT COUNT(*) FROM messages AS replies WHERE replies.thread_root = messages.id) AS reply_count,
+  COALESCE((
+    SELECT json_agg(json_build_object('emoji', emoji, 'count', count) ORDER BY first_reacted_at)
+    FROM (
+      SELECT emoji, COUNT(*) AS count, MIN(created_at) AS first_reacted_at
+      FROM reactions WHERE message_id = messages.id GROUP BY emoji
+    ) AS counts
+  ), '[]') AS reactions,
+  COALESCE((
+    SELECT json_agg(json_build_object(
+      'id', id, 'filename', filename, 'contentType', content_type, 'size', size
+    ) ORDER BY created_at)
+    FROM attachments WHERE message_id = messages.id
+  ), '[]') AS attachments,
+  COALESCE((
+    SELECT json_agg(json_build_object('id', users.id, 'username', users.username) ORDER BY users.username)
+    FROM mentions JOIN users ON users.id = mentions.user_id
+    WHERE mentions.message_id = messages.id
+  ), '[]') AS mentions
+FROM messages;
@@ -0,0 +1,72 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::async_trait;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Mutex,
+};
+
+use crate::{
+    common::{Envelope, Subscription, Subscriptions},
+    event::Event,
+};
+
+use super::EventBus;
+
+/// Delivers events within a single process.
+#[derive(Debug)]
+pub struct LocalBus {
+    next_id: AtomicU64,
+
+    tx: UnboundedSender<(Subscription, Envelope)>,
+    rx: Mutex<UnboundedReceiver<(Subscription, Envelope)>>,
+}
+
+impl Default for LocalBus {
+    fn default() -> Self {
+        // Seeding ids from the clock keeps them increasing across restarts,
+        // so a Last-Event-ID issued by a previous process is detected as a gap.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_micros() as u64)
+            .unwrap_or_default();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        Self {
+            next_id: AtomicU64::new(seed),
+            tx,
+            rx: Mutex::new(rx),
+        }
+    }
+}
+
+#[async_trait]
+impl EventBus for LocalBus {
+    async fn latest_id(&self) -> anyhow::Result<u64> {
+        Ok(self.next_id.load(Ordering::Relaxed) - 1)
+    }
+
+    async fn publish(&self, subscription: Subscription, event: Event) -> anyhow::Result<()> {
+        let envelope = Envelope {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            event,
+        };
+
+        self.tx.send((subscription, envelope))?;
+        Ok(())
+    }
+
+    async fn listen(&self, subscriptions: Subscriptions) -> anyhow::Result<()> {
+        let mut rx = self.rx.lock().await;
+
+        while let Some((subscription, envelope)) = rx.recv().await {
+            subscriptions.deliver(&subscription, envelope);
+        }
+
+        Ok(())
+    }
+}
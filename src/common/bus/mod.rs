@@ -0,0 +1,24 @@
+pub mod local;
+pub mod postgres;
+
+use axum::async_trait;
+
+use crate::event::Event;
+
+use super::{Subscription, Subscriptions};
+
+pub use local::LocalBus;
+pub use postgres::PostgresBus;
+
+/// Carries events between every running instance of the server.
+#[async_trait]
+pub trait EventBus: Send + Sync + 'static {
+    /// Returns the id of the newest event published so far.
+    async fn latest_id(&self) -> anyhow::Result<u64>;
+
+    /// Assigns the event an id and publishes it to every instance, this one included.
+    async fn publish(&self, subscription: Subscription, event: Event) -> anyhow::Result<()>;
+
+    /// Hands events published by any instance over to `subscriptions` until the bus shuts down.
+    async fn listen(&self, subscriptions: Subscriptions) -> anyhow::Result<()>;
+}
@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use axum::async_trait;
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::time::{interval, sleep_until, Instant};
+
+use crate::{
+    common::{Envelope, Subscription, Subscriptions},
+    event::Event,
+};
+
+use super::EventBus;
+
+/// Fans events out to every instance sharing the database through `LISTEN`/`NOTIFY`.
+///
+/// Events are written to the `events` table and only their id is notified, so
+/// payloads aren't bound by the NOTIFY size limit. Ids come from the
+/// `event_ids` sequence, so cursors stay valid when a client reconnects to a
+/// different instance.
+#[derive(Debug, Clone)]
+pub struct PostgresBus {
+    pool: PgPool,
+}
+
+impl PostgresBus {
+    const CHANNEL: &str = "taqui_events";
+    const MIN_BACKOFF: Duration = Duration::from_millis(500);
+    const MAX_BACKOFF: Duration = Duration::from_secs(30);
+    /// How many events are read from the table at once.
+    const BATCH_SIZE: i64 = 256;
+    /// How long a missing id holds back later events before it's assumed to be rolled back.
+    const GAP_TIMEOUT: Duration = Duration::from_secs(2);
+    const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Listens until the connection drops, resetting `backoff` once `LISTEN` succeeds.
+    async fn follow(
+        &self,
+        subscriptions: &Subscriptions,
+        backoff: &mut Duration,
+    ) -> anyhow::Result<()> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(Self::CHANNEL).await?;
+        let mut cursor = self.latest_id().await?;
+        *backoff = Self::MIN_BACKOFF;
+
+        // Anything published before LISTEN took effect never reached this instance.
+        subscriptions.interrupt(cursor);
+
+        let mut gap_since = None;
+        let mut cleanup = interval(Self::CLEANUP_INTERVAL);
+
+        loop {
+            let retry_at = gap_since.map(|since| since + Self::GAP_TIMEOUT);
+
+            tokio::select! {
+                notification = listener.try_recv() => {
+                    if notification?.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = sleep_until(retry_at.unwrap_or_else(Instant::now)), if retry_at.is_some() => {}
+                _ = cleanup.tick() => {
+                    self.cleanup().await?;
+                    continue;
+                }
+            }
+
+            self.catch_up(subscriptions, &mut cursor, &mut gap_since)
+                .await?;
+        }
+    }
+
+    /// Delivers every stored event after `cursor` in id order.
+    ///
+    /// Ids are taken before commit, so a later id can become visible first. Delivery
+    /// stops at a missing id until it shows up or [`Self::GAP_TIMEOUT`] passes.
+    async fn catch_up(
+        &self,
+        subscriptions: &Subscriptions,
+        cursor: &mut u64,
+        gap_since: &mut Option<Instant>,
+    ) -> anyhow::Result<()> {
+        loop {
+            let events = sqlx::query!(
+                r#"SELECT id, subscription, event FROM events
+                   WHERE id > $1
+                   ORDER BY id
+                   LIMIT $2"#,
+                *cursor as i64,
+                Self::BATCH_SIZE
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let count = events.len() as i64;
+
+            for event in events {
+                let id = event.id as u64;
+
+                if id != *cursor + 1 {
+                    let since = *gap_since.get_or_insert_with(Instant::now);
+
+                    if since.elapsed() < Self::GAP_TIMEOUT {
+                        return Ok(());
+                    }
+                }
+
+                *gap_since = None;
+                *cursor = id;
+
+                match (
+                    serde_json::from_str::<Subscription>(&event.subscription),
+                    serde_json::from_str::<Event>(&event.event),
+                ) {
+                    (Ok(subscription), Ok(event)) => {
+                        subscriptions.deliver(&subscription, Envelope { id, event })
+                    }
+                    (Err(error), _) | (_, Err(error)) => {
+                        tracing::error!("malformed event {id}: {error}")
+                    }
+                }
+            }
+
+            if count < Self::BATCH_SIZE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Removes events every listener has long since read.
+    async fn cleanup(&self) -> anyhow::Result<()> {
+        sqlx::query!(
+            "DELETE FROM events WHERE created_at < (now() AT TIME ZONE 'UTC') - interval '10 minutes'"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventBus for PostgresBus {
+    async fn latest_id(&self) -> anyhow::Result<u64> {
+        // The newest committed id, later ids still in flight are delivered once they commit.
+        let latest_id = sqlx::query_scalar!(
+            r#"SELECT COALESCE(
+                 (SELECT MAX(id) FROM events),
+                 (SELECT last_value FROM event_ids)
+               ) AS "latest_id!""#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(latest_id as u64)
+    }
+
+    async fn publish(&self, subscription: Subscription, event: Event) -> anyhow::Result<()> {
+        let subscription = serde_json::to_string(&subscription)?;
+        let event = serde_json::to_string(&event)?;
+
+        sqlx::query_scalar!("SELECT publish_event($1, $2)", subscription, event)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn listen(&self, subscriptions: Subscriptions) -> anyhow::Result<()> {
+        let mut backoff = Self::MIN_BACKOFF;
+
+        loop {
+            match self.follow(&subscriptions, &mut backoff).await {
+                Ok(()) => tracing::warn!("event bus connection lost, reconnecting"),
+                Err(error) => {
+                    tracing::warn!("event bus connection failed: {error}, retrying in {backoff:?}")
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(Self::MAX_BACKOFF);
+        }
+    }
+}
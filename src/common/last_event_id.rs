@@ -0,0 +1,30 @@
+use std::convert::Infallible;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+
+/// The `Last-Event-ID` header sent by reconnecting event stream clients.
+///
+/// A header that can't be parsed resolves to id `0`, which always predates the
+/// replay buffer, so the client is told to resync instead of silently resuming.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LastEventId(pub Option<u64>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for LastEventId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let last_event_id = parts.headers.get("last-event-id").map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or_default()
+        });
+
+        Ok(Self(last_event_id))
+    }
+}
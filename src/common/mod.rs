@@ -1,5 +1,9 @@
+pub mod bus;
 pub mod garde;
+pub mod last_event_id;
+pub mod storage;
 pub mod subscriptions;
+pub mod totp;
 pub mod typing;
 pub mod turnstile;
 
@@ -13,13 +17,16 @@ use axum::{
     Router,
 };
 use futures_util::Stream;
-use std::{convert::Infallible, time::Duration};
-use tokio_stream::{wrappers::BroadcastStream, StreamExt};
+use std::{convert::Infallible, future::Future, time::Duration};
+use tokio_stream::StreamExt;
 use tower_layer::Layer;
 use tower_service::Service;
+use uuid::Uuid;
 
 pub use garde::{Garde, MappedRejection};
-pub use subscriptions::{Subscription, Subscriptions};
+pub use last_event_id::LastEventId;
+pub use storage::{LocalStorage, S3Storage, Storage};
+pub use subscriptions::{Envelope, EventStream, Subscription, Subscriptions};
 pub use typing::{Indicator, IndicatorKey, Indicators};
 
 pub trait RouterExt<S>
@@ -53,15 +60,35 @@ where
     }
 }
 
+const SSE_EVENT_NAME: &str = "taqui";
+
 pub fn sse_to_subscription(
     buckets: &Subscriptions,
     bucket: &Subscription,
+    user_id: Uuid,
+    last_event_id: LastEventId,
+    revoked: impl Future<Output = ()> + Send + 'static,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    sse_from_stream(
+        buckets.stream([*bucket], last_event_id.0).for_user(user_id),
+        revoked,
+    )
+}
+
+/// Streams events until `revoked` resolves, after which the client has to
+/// reconnect and authenticate again.
+pub fn sse_from_stream(
+    events: EventStream,
+    revoked: impl Future<Output = ()> + Send + 'static,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = buckets.subscribe(bucket);
+    let stream = futures_util::StreamExt::take_until(events, revoked).map(|envelope| {
+        let data = serde_json::to_string(&envelope.event).expect("failed to seralize event");
 
-    let stream = BroadcastStream::new(rx)
-        .filter_map(|result| result.ok())
-        .map(Ok);
+        Ok(Event::default()
+            .id(envelope.id.to_string())
+            .event(SSE_EVENT_NAME)
+            .data(data))
+    });
 
     Sse::new(stream).keep_alive(
         KeepAlive::new()
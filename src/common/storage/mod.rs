@@ -0,0 +1,77 @@
+pub mod local;
+pub mod s3;
+
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+use axum::{async_trait, body::Bytes};
+use sqlx::PgPool;
+use tokio::time::interval;
+
+use crate::models::attachment::OrphanedFile;
+
+pub use local::LocalStorage;
+pub use s3::S3Storage;
+
+/// Keeps uploaded files, addressed by slash-separated keys such as
+/// `icons/<group_id>/<hash>.png`.
+#[async_trait]
+pub trait Storage: Debug + Send + Sync + 'static {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> anyhow::Result<()>;
+
+    /// Returns the file stored under `key`, or `None` if there is none.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>>;
+
+    /// Deletes the file stored under `key`, if there is one.
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+}
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+const CLEANUP_BATCH_SIZE: i64 = 100;
+
+/// Periodically removes the files of deleted attachments from storage.
+pub fn spawn_cleanup(storage: Arc<dyn Storage>, pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = interval(CLEANUP_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let files = match OrphanedFile::fetch_all(CLEANUP_BATCH_SIZE, &pool).await {
+                Ok(files) => files,
+                Err(_) => continue,
+            };
+
+            for file in files {
+                if let Err(error) = storage.delete(&file.key).await {
+                    tracing::warn!("failed to delete orphaned file {}: {error}", file.key);
+                    continue;
+                }
+
+                let _ = OrphanedFile::delete(&file.key, &pool).await;
+            }
+        }
+    });
+}
+
+/// Guesses the file extension of an image from its magic bytes, returning
+/// `None` for anything that isn't a PNG, JPEG, GIF or WebP image.
+pub fn image_extension(data: &[u8]) -> Option<&'static str> {
+    match data {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("jpg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("webp"),
+        _ => None,
+    }
+}
+
+/// Content type of a file, guessed from the extension of its key.
+pub fn content_type(key: &str) -> &'static str {
+    match key.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("png") => "image/png",
+        Some("jpg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
@@ -1,40 +1,555 @@
-use std::sync::Arc;
-use axum::response::sse::{self};
-use dashmap::DashMap;
-use tokio::sync::broadcast;
+use dashmap::{mapref::one::Ref, DashMap};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{interval, Instant},
+};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamMap,
+};
 use uuid::Uuid;
 
-use crate::event::Event;
+use crate::{
+    event::Event,
+    models::{self, Group},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use super::bus::EventBus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum Subscription {
     Group(Uuid),
+    Channel(Uuid),
+    User(Uuid),
+}
+
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub id: u64,
+    pub event: Event,
+}
+
+#[derive(Debug)]
+struct History {
+    events: VecDeque<Envelope>,
+    /// Events with an id up to and including the horizon can no longer be replayed.
+    horizon: u64,
+    latest: u64,
+}
+
+impl History {
+    const CAPACITY: usize = 256;
+
+    fn new(horizon: u64) -> Self {
+        Self {
+            events: VecDeque::with_capacity(Self::CAPACITY),
+            horizon,
+            latest: horizon,
+        }
+    }
+
+    fn push(&mut self, envelope: Envelope) {
+        if self.events.len() == Self::CAPACITY {
+            if let Some(evicted) = self.events.pop_front() {
+                self.horizon = evicted.id;
+            }
+        }
+
+        self.latest = envelope.id;
+        self.events.push_back(envelope);
+    }
+
+    fn replay(&self, last_id: u64) -> Option<Vec<Envelope>> {
+        if last_id < self.horizon {
+            return None;
+        }
+
+        let events = self
+            .events
+            .iter()
+            .filter(|envelope| envelope.id > last_id)
+            .cloned()
+            .collect();
+
+        Some(events)
+    }
+}
+
+#[derive(Debug)]
+struct Channel {
+    tx: broadcast::Sender<Envelope>,
+    history: Mutex<History>,
+    /// When the sweeper first saw this channel without receivers.
+    idle_since: Option<Instant>,
+}
+
+impl Channel {
+    fn new(horizon: u64) -> Self {
+        let (tx, ..) = broadcast::channel(128);
+
+        Self {
+            tx,
+            history: Mutex::new(History::new(horizon)),
+            idle_since: None,
+        }
+    }
+
+    /// Whether the channel has had no receivers for at least `timeout`.
+    fn is_expired(&mut self, timeout: Duration) -> bool {
+        if self.tx.receiver_count() > 0 {
+            self.idle_since = None;
+            return false;
+        }
+
+        self.idle_since.get_or_insert_with(Instant::now).elapsed() >= timeout
+    }
+}
+
+struct Resumed {
+    rx: broadcast::Receiver<Envelope>,
+    replay: Option<Vec<Envelope>>,
+    latest: u64,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct Subscriptions {
-    subscriptions: Arc<DashMap<Subscription, broadcast::Sender<sse::Event>>>,
+    channels: Arc<DashMap<Subscription, Channel>>,
+    latest_id: Arc<AtomicU64>,
+    outbox: mpsc::UnboundedSender<(Subscription, Event)>,
 }
 
 impl Subscriptions {
-    const EVENT_NAME: &str = "taqui";
+    const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+    /// Channels without receivers are kept around this long so that
+    /// reconnecting clients can still replay what they missed.
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    pub async fn new(bus: impl EventBus) -> anyhow::Result<Self> {
+        let bus = Arc::new(bus);
+        let (outbox, mut rx) = mpsc::unbounded_channel();
+
+        let subscriptions = Self {
+            channels: Arc::default(),
+            latest_id: Arc::new(AtomicU64::new(bus.latest_id().await?)),
+            outbox,
+        };
+
+        tokio::spawn({
+            let bus = bus.clone();
+            let subscriptions = subscriptions.clone();
+
+            async move {
+                if let Err(error) = bus.listen(subscriptions).await {
+                    tracing::error!("event bus listener stopped: {error}");
+                }
+            }
+        });
+
+        // Publishing from a single task keeps events in the order they were sent.
+        tokio::spawn(async move {
+            while let Some((subscription, event)) = rx.recv().await {
+                if let Err(error) = bus.publish(subscription, event).await {
+                    tracing::error!("failed to publish event: {error}");
+                }
+            }
+        });
+
+        tokio::spawn({
+            let subscriptions = subscriptions.clone();
+
+            async move {
+                let mut sweep = interval(Self::SWEEP_INTERVAL);
+
+                loop {
+                    sweep.tick().await;
+                    subscriptions.sweep();
+                }
+            }
+        });
+
+        Ok(subscriptions)
+    }
+
+    /// Number of live channels, including idle ones awaiting collection.
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Number of receivers across every channel.
+    pub fn receiver_count(&self) -> usize {
+        self.channels
+            .iter()
+            .map(|channel| channel.tx.receiver_count())
+            .sum()
+    }
+
+    /// Drops channels that have been without receivers for longer than
+    /// [`Self::IDLE_TIMEOUT`], along with their replay history.
+    pub fn sweep(&self) {
+        self.channels
+            .retain(|_, channel| !channel.is_expired(Self::IDLE_TIMEOUT));
+
+        tracing::debug!(channels = self.channels.len(), "swept idle channels");
+    }
 
     pub fn send(&self, event: &Event, subscription: &Subscription) {
-        if let Some(tx) = self.subscriptions.get(subscription) {
-            let event = serde_json::to_string(event).expect("failed to seralize event");
-            let event = sse::Event::default().event(Self::EVENT_NAME).data(event);
+        let _ = self.outbox.send((*subscription, event.clone()));
+    }
+
+    /// Sends an event happening in a channel to the channel and to its group.
+    pub fn send_to_channel(&self, event: &Event, channel: &models::Channel) {
+        self.send(event, &Subscription::Group(channel.group_id));
+        self.send(event, &Subscription::Channel(channel.id));
+    }
 
-            let _ = tx.send(event);
+    /// Sends a group-wide event to the group as well as to each of its channels,
+    /// so that streams following a single channel see it too.
+    pub fn broadcast(&self, event: &Event, group_id: Uuid, channels: &[models::Channel]) {
+        self.send(event, &Subscription::Group(group_id));
+
+        for channel in channels {
+            self.send(event, &Subscription::Channel(channel.id));
         }
     }
 
-    pub fn subscribe(&self, subscription: &Subscription) -> broadcast::Receiver<sse::Event> {
-        if let Some(tx) = self.subscriptions.get(&subscription) {
-            return tx.subscribe();
+    /// Hands an event received from the bus to local subscribers.
+    pub fn deliver(&self, subscription: &Subscription, envelope: Envelope) {
+        self.latest_id.fetch_max(envelope.id, Ordering::Relaxed);
+
+        if let Some(channel) = self.channels.get(subscription) {
+            let mut history = channel.history.lock().expect("history lock poisoned");
+
+            history.push(envelope.clone());
+            let _ = channel.tx.send(envelope);
         }
+    }
+
+    /// Tells every local subscriber to resync because events up to `latest_id`
+    /// may have been lost in transit.
+    pub fn interrupt(&self, latest_id: u64) {
+        self.latest_id.fetch_max(latest_id, Ordering::Relaxed);
 
-        let (tx, rx) = broadcast::channel(128);
-        self.subscriptions.insert(*subscription, tx);
-        rx
+        for channel in self.channels.iter() {
+            let mut history = channel.history.lock().expect("history lock poisoned");
+
+            history.events.clear();
+            history.horizon = latest_id;
+            history.latest = history.latest.max(latest_id);
+
+            let _ = channel.tx.send(Envelope {
+                id: latest_id,
+                event: Event::ResyncRequired,
+            });
+        }
+    }
+
+    pub fn stream(
+        &self,
+        subscriptions: impl IntoIterator<Item = Subscription>,
+        last_id: Option<u64>,
+    ) -> EventStream {
+        let mut stream = EventStream {
+            subscriptions: self.clone(),
+            user_id: None,
+            streams: StreamMap::new(),
+            cursors: HashMap::new(),
+            pending: BinaryHeap::new(),
+            position: 0,
+            resync: false,
+            resynced: 0,
+        };
+
+        let mut replay = Vec::new();
+        let mut gap = false;
+
+        for subscription in subscriptions {
+            let resumed = self.subscribe(&subscription, last_id);
+
+            match resumed.replay {
+                Some(events) => replay.extend(
+                    events
+                        .into_iter()
+                        .map(|envelope| Queued(subscription, envelope)),
+                ),
+                None => gap = true,
+            }
+
+            stream.add(subscription, resumed.rx, last_id.unwrap_or(resumed.latest));
+        }
+
+        if gap {
+            stream.resync();
+        } else {
+            stream.pending.extend(replay);
+        }
+
+        stream
+    }
+
+    pub fn subscribe_user(
+        &self,
+        user_id: Uuid,
+        groups: &[Group],
+        last_id: Option<u64>,
+    ) -> EventStream {
+        let subscriptions = groups
+            .iter()
+            .map(|group| Subscription::Group(group.id))
+            .chain([Subscription::User(user_id)]);
+
+        self.stream(subscriptions, last_id).for_user(user_id)
+    }
+
+    fn latest_id(&self) -> u64 {
+        self.latest_id.load(Ordering::Relaxed)
+    }
+
+    fn channel(&self, subscription: &Subscription) -> Ref<'_, Subscription, Channel> {
+        self.channels
+            .entry(*subscription)
+            .or_insert_with(|| Channel::new(self.latest_id()))
+            .downgrade()
+    }
+
+    fn subscribe(&self, subscription: &Subscription, last_id: Option<u64>) -> Resumed {
+        let channel = self.channel(subscription);
+        let history = channel.history.lock().expect("history lock poisoned");
+
+        Resumed {
+            rx: channel.tx.subscribe(),
+            replay: match last_id {
+                // A cursor from the future was issued by another event id sequence.
+                Some(last_id) if last_id > self.latest_id() => None,
+                Some(last_id) => history.replay(last_id),
+                None => Some(Vec::new()),
+            },
+            latest: history.latest,
+        }
+    }
+
+    fn replay(&self, subscription: &Subscription, last_id: u64) -> Option<Vec<Envelope>> {
+        let channel = self.channels.get(subscription)?;
+        let history = channel.history.lock().expect("history lock poisoned");
+
+        history.replay(last_id)
+    }
+}
+
+/// An event waiting to be yielded, ordered so the smallest id is popped first.
+#[derive(Debug)]
+struct Queued(Subscription, Envelope);
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.1.id == other.1.id
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        other.1.id.cmp(&self.1.id)
+    }
+}
+
+/// Merges one or more subscriptions into a single resumable stream of events,
+/// following group joins, departures and deletions, as well as channel
+/// deletions, as they arrive.
+///
+/// Events are yielded in id order, so the id of a yielded envelope is the
+/// position to resume the whole stream from.
+pub struct EventStream {
+    subscriptions: Subscriptions,
+    /// The user the stream is delivered to, whose removal from a group ends
+    /// the subscription to it.
+    user_id: Option<Uuid>,
+    streams: StreamMap<Subscription, BroadcastStream<Envelope>>,
+    cursors: HashMap<Subscription, u64>,
+    pending: BinaryHeap<Queued>,
+    position: u64,
+    resync: bool,
+    resynced: u64,
+}
+
+impl EventStream {
+    pub fn for_user(mut self, user_id: Uuid) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    fn add(&mut self, subscription: Subscription, rx: broadcast::Receiver<Envelope>, cursor: u64) {
+        self.streams.insert(subscription, BroadcastStream::new(rx));
+        self.cursors.insert(subscription, cursor);
+        self.position = self.position.max(cursor);
+    }
+
+    fn remove(&mut self, subscription: &Subscription) {
+        self.streams.remove(subscription);
+        self.cursors.remove(subscription);
+    }
+
+    /// Stops following a group, along with the channel subscription the event
+    /// saying so arrived on.
+    fn leave(&mut self, via: Subscription, group_id: Uuid) {
+        self.remove(&Subscription::Group(group_id));
+
+        if let Subscription::Channel(..) = via {
+            self.remove(&via);
+        }
+    }
+
+    /// Drops anything queued and tells the client to refetch its state,
+    /// skipping every cursor ahead to the newest event.
+    fn resync(&mut self) {
+        self.pending.clear();
+
+        let latest = self.subscriptions.latest_id();
+        for cursor in self.cursors.values_mut() {
+            *cursor = latest;
+        }
+
+        self.position = latest;
+        self.resync = true;
+        self.resynced = latest;
+    }
+
+    fn recover(&mut self, subscription: Subscription) {
+        let cursor = self.cursors.get(&subscription).copied().unwrap_or_default();
+
+        match self.subscriptions.replay(&subscription, cursor) {
+            Some(events) => self.pending.extend(
+                events
+                    .into_iter()
+                    .map(|envelope| Queued(subscription, envelope)),
+            ),
+            None => self.resync(),
+        }
+    }
+
+    fn accept(&mut self, subscription: Subscription, envelope: Envelope) -> Option<Envelope> {
+        if let Event::ResyncRequired = envelope.event {
+            if envelope.id > self.resynced {
+                self.resync();
+            }
+
+            return None;
+        }
+
+        let cursor = self.cursors.get_mut(&subscription)?;
+        if envelope.id <= *cursor {
+            return None;
+        }
+
+        *cursor = envelope.id;
+        self.position = self.position.max(envelope.id);
+
+        match &envelope.event {
+            // Events published to the group after the join, such as the member
+            // being added, may have already been delivered, so they're replayed.
+            Event::JoinGroup(group) => {
+                let subscription = Subscription::Group(group.id);
+                let resumed = self
+                    .subscriptions
+                    .subscribe(&subscription, Some(envelope.id));
+
+                self.add(subscription, resumed.rx, envelope.id);
+
+                match resumed.replay {
+                    Some(events) => self.pending.extend(
+                        events
+                            .into_iter()
+                            .map(|envelope| Queued(subscription, envelope)),
+                    ),
+                    None => self.resync(),
+                }
+            }
+            Event::LeaveGroup(event) => self.remove(&Subscription::Group(event.group_id)),
+            Event::RemoveMember(event) if Some(event.user_id) == self.user_id => {
+                self.leave(subscription, event.group_id)
+            }
+            Event::DeleteGroup(event) => self.leave(subscription, event.group_id),
+            Event::DeleteChannel(event) => self.remove(&Subscription::Channel(event.channel_id)),
+            _ => {}
+        }
+
+        Some(envelope)
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Envelope;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Envelope>> {
+        loop {
+            if self.resync {
+                self.resync = false;
+
+                return Poll::Ready(Some(Envelope {
+                    id: self.position,
+                    event: Event::ResyncRequired,
+                }));
+            }
+
+            // Events are delivered to every subscription in id order, so once all
+            // the receivers are drained nothing arriving later can have a smaller
+            // id than what's queued.
+            let mut ended = false;
+
+            loop {
+                match Pin::new(&mut self.streams).poll_next(cx) {
+                    Poll::Ready(Some((subscription, Ok(envelope)))) => {
+                        self.pending.push(Queued(subscription, envelope))
+                    }
+                    Poll::Ready(Some((
+                        subscription,
+                        Err(BroadcastStreamRecvError::Lagged(..)),
+                    ))) => self.recover(subscription),
+                    Poll::Ready(None) => {
+                        ended = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            }
+
+            if self.resync {
+                continue;
+            }
+
+            match self.pending.pop() {
+                Some(Queued(subscription, envelope)) => {
+                    if let Some(envelope) = self.accept(subscription, envelope) {
+                        return Poll::Ready(Some(envelope));
+                    }
+                }
+                None if ended => return Poll::Ready(None),
+                None => return Poll::Pending,
+            }
+        }
     }
 }
@@ -0,0 +1,82 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{rngs::OsRng, Rng, RngCore};
+use sha2::{Digest, Sha256};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::Error;
+
+const ISSUER: &str = "taqui";
+const SECRET_LENGTH: usize = 20;
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// Steps either side of the current one that are accepted, to allow for clock drift.
+const SKEW: u64 = 1;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+/// Lowercase letters and digits without the easily confused `0`, `1`, `i`, `l` and `o`.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Generates a new base32 encoded shared secret.
+pub fn generate_secret() -> String {
+    let mut secret = [0; SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+
+    match Secret::Raw(secret.to_vec()).to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(..) => unreachable!("secret was just encoded"),
+    }
+}
+
+fn totp(secret: &str, account_name: &str) -> Result<TOTP, Error> {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes()?;
+
+    Ok(TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP,
+        secret,
+        Some(ISSUER.to_owned()),
+        account_name.to_owned(),
+    )?)
+}
+
+pub fn otpauth_uri(secret: &str, account_name: &str) -> Result<String, Error> {
+    Ok(totp(secret, account_name)?.get_url())
+}
+
+/// Checks a code against the current time, returning the time step it belongs to.
+pub fn verify(secret: &str, account_name: &str, code: &str) -> Result<Option<u64>, Error> {
+    let totp = totp(secret, account_name)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the unix epoch")
+        .as_secs();
+    let current = now / STEP;
+
+    Ok((current - SKEW..=current + SKEW).find(|step| totp.check(code, step * STEP)))
+}
+
+/// Generates a recovery code formatted as two dash separated halves, e.g. `abcde-23456`.
+pub fn generate_recovery_code() -> String {
+    let code = (0..RECOVERY_CODE_LENGTH)
+        .map(|_| RECOVERY_CODE_ALPHABET[OsRng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect::<String>();
+
+    let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+    format!("{first}-{second}")
+}
+
+/// Hashes a recovery code, ignoring case and dashes.
+pub fn hash_recovery_code(code: &str) -> String {
+    let code = code
+        .chars()
+        .filter(|char| *char != '-')
+        .map(|char| char.to_ascii_lowercase())
+        .collect::<String>();
+
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
@@ -1,21 +1,16 @@
-use std::{
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
-    time::Duration,
-};
+use std::{sync::Arc, time::Duration};
 
-use dashmap::DashMap;
+use dashmap::{mapref::entry::Entry, DashMap};
 use tokio::{
     select,
-    sync::broadcast::{self, Sender},
+    sync::mpsc::{self, UnboundedSender},
     time::{sleep, Instant},
 };
 use uuid::Uuid;
 
 use crate::{
-    common::Subscription, event::{EndTypingEvent, Event, StartTypingEvent}, models::{Group, User}
+    event::{EndTypingEvent, Event, StartTypingEvent},
+    models::{Channel, User},
 };
 
 use super::Subscriptions;
@@ -26,88 +21,96 @@ pub enum TypingEvent {
     End,
 }
 
+/// Handle to a running indicator task. The task removes its own entry from
+/// [`Indicators`] once it stops typing.
 #[derive(Debug)]
 pub struct Indicator {
-    tx: Sender<TypingEvent>,
-    is_active: Arc<AtomicBool>,
+    tx: UnboundedSender<TypingEvent>,
 }
 
 impl Indicator {
     const DEFAULT_INDICATOR_TIMEOUT: Duration = Duration::from_secs(7);
 
-    pub fn new() -> Self {
-        let (tx, ..) = broadcast::channel(128);
-
-        Self {
-            tx,
-            is_active: Arc::new(AtomicBool::new(false)),
-        }
-    }
-
-    pub fn start_typing(&mut self, user: User, group: Group, subscriptions: Subscriptions) {
-        if self.is_active.load(Ordering::Relaxed) {
-            self.tx
-                .send(TypingEvent::Start)
-                .expect("failed to send typing event");
-            return;
-        }
-
-        let mut rx = self.tx.subscribe();
-        let is_active = self.is_active.clone();
+    fn spawn(
+        key: IndicatorKey,
+        user: User,
+        channel: Channel,
+        subscriptions: Subscriptions,
+        indicators: Indicators,
+    ) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handle = tx.clone();
 
         tokio::spawn(async move {
             let timer = sleep(Indicator::DEFAULT_INDICATOR_TIMEOUT);
             tokio::pin!(timer);
 
-            subscriptions.send(
+            subscriptions.send_to_channel(
                 &Event::StartTyping(StartTypingEvent {
-                    group_id: group.id,
+                    group_id: channel.group_id,
+                    channel_id: channel.id,
                     user: user.clone(),
                 }),
-                &Subscription::Group(group.id),
+                &channel,
             );
 
             loop {
-                is_active.store(true, Ordering::Relaxed);
-
-                select! {
-                    _ = &mut timer => {
-                        break
-                    }
-                    Ok(event) = rx.recv() => match event {
-                        TypingEvent::Start => {
-                            subscriptions.send(
-                                &Event::StartTyping(StartTypingEvent {
-                                    group_id: group.id,
-                                    user: user.clone(),
-                                }),
-                                &Subscription::Group(group.id),
-                            );
-
-                            timer.as_mut().reset(Instant::now() + Indicator::DEFAULT_INDICATOR_TIMEOUT)
-                        },
-                        TypingEvent::End => break
+                loop {
+                    select! {
+                        _ = &mut timer => {
+                            break
+                        }
+                        Some(event) = rx.recv() => match event {
+                            TypingEvent::Start => {
+                                subscriptions.send_to_channel(
+                                    &Event::StartTyping(StartTypingEvent {
+                                        group_id: channel.group_id,
+                                        channel_id: channel.id,
+                                        user: user.clone(),
+                                    }),
+                                    &channel,
+                                );
+
+                                timer.as_mut().reset(Instant::now() + Indicator::DEFAULT_INDICATOR_TIMEOUT)
+                            },
+                            TypingEvent::End => break
+                        }
                     }
                 }
-            }
 
-            is_active.store(false, Ordering::Relaxed);
+                // Starts are sent while the map entry is held, so checking for queued
+                // ones under the same lock means none get lost with the entry.
+                let stopped = indicators.indicators.remove_if(&key, |_, indicator| {
+                    indicator.tx.same_channel(&handle) && rx.is_empty()
+                });
+
+                if stopped.is_some() || rx.is_empty() {
+                    break;
+                }
 
-            subscriptions.send(
+                timer
+                    .as_mut()
+                    .reset(Instant::now() + Indicator::DEFAULT_INDICATOR_TIMEOUT);
+            }
+
+            subscriptions.send_to_channel(
                 &Event::EndTyping(EndTypingEvent {
-                    group_id: group.id,
+                    group_id: channel.group_id,
+                    channel_id: channel.id,
                     user: user.clone(),
                 }),
-                &Subscription::Group(group.id),
+                &channel,
             );
         });
+
+        Self { tx }
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct IndicatorKey {
     pub user_id: Uuid,
-    pub group_id: Uuid,
+    pub channel_id: Uuid,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -116,28 +119,45 @@ pub struct Indicators {
 }
 
 impl Indicators {
-    pub fn start_typing(&self, user: &User, group: &Group, subscriptions: &Subscriptions) {
+    pub fn start_typing(&self, user: &User, channel: &Channel, subscriptions: &Subscriptions) {
         let key = IndicatorKey {
             user_id: user.id,
-            group_id: group.id,
+            channel_id: channel.id,
         };
 
-        let mut indicator = self
-            .indicators
-            .entry(key)
-            .or_insert_with(|| Indicator::new());
-
-        indicator.start_typing(user.clone(), group.clone(), subscriptions.clone());
+        match self.indicators.entry(key) {
+            Entry::Occupied(entry) => {
+                let _ = entry.get().tx.send(TypingEvent::Start);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Indicator::spawn(
+                    key,
+                    user.clone(),
+                    channel.clone(),
+                    subscriptions.clone(),
+                    self.clone(),
+                ));
+            }
+        }
     }
 
-    pub fn end_typing(&self, user: &User, group: &Group) {
+    pub fn end_typing(&self, user: &User, channel: &Channel) {
         let key = IndicatorKey {
             user_id: user.id,
-            group_id: group.id,
+            channel_id: channel.id,
         };
 
         if let Some(indicator) = self.indicators.get(&key) {
             let _ = indicator.tx.send(TypingEvent::End);
         }
     }
+
+    /// Number of users currently typing across every channel.
+    pub fn len(&self) -> usize {
+        self.indicators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indicators.is_empty()
+    }
 }
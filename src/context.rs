@@ -4,7 +4,8 @@ use axum::extract::FromRef;
 use sqlx::PgPool;
 
 use crate::{
-    common::{turnstile::TurnstileClient, Indicators, Subscriptions},
+    common::{turnstile::TurnstileClient, Indicators, Storage, Subscriptions},
+    models::invite::InviteCodes,
     rate_limit::Buckets,
 };
 
@@ -40,19 +41,34 @@ pub struct Context {
     buckets: Buckets,
     indicators: Indicators,
     turnstile: TurnstileClient,
+    storage: Arc<dyn Storage>,
+    invite_codes: InviteCodes,
+    /// Bearer token required to read metrics, which aren't served without one.
+    metrics_token: Option<Arc<str>>,
 
     _args: (),
 }
 
 impl Context {
-    pub fn new(pool: Arc<PgPool>, keys: Keys, turnstile_secret: impl Into<Arc<str>>) -> Self {
+    pub fn new(
+        pool: Arc<PgPool>,
+        keys: Keys,
+        subscriptions: Subscriptions,
+        turnstile_secret: impl Into<Arc<str>>,
+        storage: Arc<dyn Storage>,
+        invite_codes: InviteCodes,
+        metrics_token: Option<String>,
+    ) -> Self {
         Self {
             pool,
             keys,
-            subscriptions: Subscriptions::default(),
+            subscriptions,
             buckets: Buckets::default(),
             indicators: Indicators::default(),
             turnstile: TurnstileClient::new(turnstile_secret),
+            storage,
+            invite_codes,
+            metrics_token: metrics_token.map(Into::into),
 
             _args: (),
         }
@@ -78,6 +94,18 @@ impl Context {
         &self.turnstile
     }
 
+    pub fn storage(&self) -> &dyn Storage {
+        &*self.storage
+    }
+
+    pub fn invite_codes(&self) -> &InviteCodes {
+        &self.invite_codes
+    }
+
+    pub fn metrics_token(&self) -> Option<&str> {
+        self.metrics_token.as_deref()
+    }
+
     pub fn keys(&self) -> &Keys {
         &self.keys
     }
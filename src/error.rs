@@ -82,11 +82,41 @@ define_code!(
         Validation = (5004, UNPROCESSABLE_ENTITY) @ "validation error",
         AlreadyMember = (5005, CONFLICT) @ "already a member",
         UnknownMessage = (5006, NOT_FOUND) @ "unknown message",
+        InvalidOp = (5007, BAD_REQUEST) @ "invalid gateway op",
+        UnknownSession = (5008, NOT_FOUND) @ "unknown session",
+        MfaAlreadyEnabled = (5009, CONFLICT) @ "two-factor authentication is already enabled",
+        MfaNotEnrolled = (5010, NOT_FOUND) @ "two-factor authentication is not enrolled",
+        UnknownRole = (5011, NOT_FOUND) @ "unknown role",
+        UnknownMember = (5012, NOT_FOUND) @ "unknown member",
+        RoleAlreadyExists = (5013, CONFLICT) @ "role with this name already exists",
+        DefaultRole = (5014, BAD_REQUEST) @ "the default role can't be deleted",
+        OwnerCannotLeave = (5015, BAD_REQUEST) @ "transfer ownership before leaving the group",
+        UnknownBan = (5016, NOT_FOUND) @ "unknown ban",
+        InvalidImage = (5017, UNPROCESSABLE_ENTITY) @ "invalid image",
+        InviteCodeTaken = (5018, CONFLICT) @ "invite code is already taken",
+        DirectMessageGroup = (5019, BAD_REQUEST) @ "not supported in direct messages",
+        CannotMessageSelf = (5020, BAD_REQUEST) @ "you can't message yourself",
+        UnknownChannel = (5021, NOT_FOUND) @ "unknown channel",
+        ChannelAlreadyExists = (5022, CONFLICT) @ "channel with this name already exists",
+        LastChannel = (5023, BAD_REQUEST) @ "the last channel of a group can't be deleted",
+        InvalidThread = (5024, BAD_REQUEST) @ "thread belongs to another channel",
+        TooManyAttachments = (5025, BAD_REQUEST) @ "too many attachments (max 10)",
+        AttachmentTooLarge = (5026, PAYLOAD_TOO_LARGE) @ "attachment is too large (max 8 MiB)",
+        UnsupportedAttachment = (5027, UNSUPPORTED_MEDIA_TYPE) @ "unsupported attachment type",
+        UnknownAttachment = (5028, NOT_FOUND) @ "unknown attachment",
+        InvalidMultipart = (5029, BAD_REQUEST) @ "invalid multipart body",
+        UnknownFile = (5030, NOT_FOUND) @ "unknown file",
+        ConflictingCursors = (5031, BAD_REQUEST) @ "only one of before, after, around and since can be used",
+        EditWindowExpired = (5032, FORBIDDEN) @ "this message can no longer be edited",
+        TooManyReactions = (5033, BAD_REQUEST) @ "too many different reactions (max 20)",
 
         InvalidToken = (6000, UNAUTHORIZED) @ "invalid token",
         InsufficientPermissions = (6001, UNAUTHORIZED) @ "insufficient permissions",
         InvalidCredentials = (6002, UNAUTHORIZED) @ "invalid credentials",
-        CaptchaFailed = (6003, UNPROCESSABLE_ENTITY) @ "captcha failed"
+        CaptchaFailed = (6003, UNPROCESSABLE_ENTITY) @ "captcha failed",
+        MfaRequired = (6004, UNAUTHORIZED) @ "two-factor authentication required",
+        InvalidMfaCode = (6005, UNAUTHORIZED) @ "invalid two-factor code",
+        Banned = (6006, FORBIDDEN) @ "you are banned from this group"
     }
 );
 
@@ -156,4 +186,8 @@ impl_from! {
     password_hash::Error;
     jsonwebtoken::errors::Error;
     reqwest::Error;
+    totp_rs::TotpUrlError;
+    totp_rs::SecretParseError;
+    std::io::Error;
+    anyhow::Error;
 }
@@ -1,37 +1,124 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::models::{message::Message, User};
+use crate::models::{message::Message, Channel, Group, GroupMember, ReadState, User};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteMessageEvent {
     pub group_id: Uuid,
+    pub channel_id: Uuid,
     pub message_id: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// A mention of the user whose stream it's sent to, either by name or
+/// through `@everyone`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionEvent {
+    pub group_id: Uuid,
+    pub channel_id: Uuid,
+    pub message_id: Uuid,
+    pub author_id: Uuid,
+    pub everyone: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionEvent {
+    pub group_id: Uuid,
+    pub channel_id: Uuid,
+    pub message_id: Uuid,
+    pub user_id: Uuid,
+    pub emoji: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StartTypingEvent {
     pub group_id: Uuid,
+    pub channel_id: Uuid,
     pub user: User
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EndTypingEvent {
     pub group_id: Uuid,
+    pub channel_id: Uuid,
     pub user: User
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteGroupEvent {
+    pub group_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteChannelEvent {
+    pub group_id: Uuid,
+    pub channel_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaveGroupEvent {
+    pub group_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddMemberEvent {
+    pub group_id: Uuid,
+    pub member: GroupMember,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RemovalReason {
+    Leave,
+    Kick,
+    Ban,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveMemberEvent {
+    pub group_id: Uuid,
+    pub user_id: Uuid,
+    pub reason: RemovalReason,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "event", content = "data")]
 #[serde(rename_all = "camelCase")]
 pub enum Event {
     NewMessage(Message),
     EditMessage(Message),
     DeleteMessage(DeleteMessageEvent),
+    Mention(MentionEvent),
+
+    ReactionAdd(ReactionEvent),
+    ReactionRemove(ReactionEvent),
     
     StartTyping(StartTypingEvent),
-    EndTyping(EndTypingEvent)
+    EndTyping(EndTypingEvent),
+
+    ReadStateUpdate(ReadState),
+
+    JoinGroup(Group),
+    UpdateGroup(Group),
+    LeaveGroup(LeaveGroupEvent),
+    DeleteGroup(DeleteGroupEvent),
+
+    CreateChannel(Channel),
+    UpdateChannel(Channel),
+    DeleteChannel(DeleteChannelEvent),
+
+    AddMember(AddMemberEvent),
+    RemoveMember(RemoveMemberEvent),
+
+    ResyncRequired,
 }
@@ -6,7 +6,12 @@ pub mod models;
 pub mod rate_limit;
 pub mod routes;
 
+use common::{
+    bus::{LocalBus, PostgresBus},
+    storage, LocalStorage, S3Storage, Storage, Subscriptions,
+};
 use context::Keys;
+use models::invite::InviteCodes;
 use tower_http::{
     cors::CorsLayer,
     services::{ServeDir, ServeFile},
@@ -39,6 +44,14 @@ async fn main() -> anyhow::Result<()> {
     let database_url = var("DATABASE_URL")?;
     let turnstile_secret = var("TURNSTILE_SECRET")?;
 
+    let invite_codes = InviteCodes::new(
+        match var("INVITE_CODE_LENGTH") {
+            Ok(length) => length.parse()?,
+            Err(_) => InviteCodes::DEFAULT_LENGTH,
+        },
+        &var("INVITE_CODE_ALPHABET").unwrap_or_else(|_| InviteCodes::DEFAULT_ALPHABET.into()),
+    )?;
+
     let pool = PgPoolOptions::new()
         .max_connections(5)
         .connect(&database_url)
@@ -46,10 +59,34 @@ async fn main() -> anyhow::Result<()> {
 
     sqlx::migrate!().run(&pool).await?;
 
+    let subscriptions = match var("EVENT_BUS").as_deref() {
+        Ok("postgres") => Subscriptions::new(PostgresBus::new(pool.clone())).await?,
+        _ => Subscriptions::new(LocalBus::default()).await?,
+    };
+
+    let storage: Arc<dyn Storage> = match var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") => Arc::new(S3Storage::new(
+            &var("S3_ENDPOINT")?,
+            &var("S3_BUCKET")?,
+            &var("S3_REGION").unwrap_or_else(|_| S3Storage::DEFAULT_REGION.into()),
+            &var("S3_ACCESS_KEY_ID")?,
+            &var("S3_SECRET_ACCESS_KEY")?,
+        )?),
+        _ => Arc::new(LocalStorage::new(
+            var("STORAGE_PATH").unwrap_or_else(|_| "storage".into()),
+        )),
+    };
+
+    storage::spawn_cleanup(storage.clone(), pool.clone());
+
     let context = Context::new(
         Arc::new(pool),
         Keys::new(jwt_public, jwt_private),
+        subscriptions,
         turnstile_secret,
+        storage,
+        invite_codes,
+        var("METRICS_TOKEN").ok().filter(|token| !token.is_empty()),
     );
 
     let addr = "0.0.0.0:3000";
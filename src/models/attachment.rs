@@ -0,0 +1,122 @@
+use axum::body::Bytes;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub key: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i32,
+}
+
+/// A file uploaded along with a new message.
+#[derive(Debug, Clone)]
+pub struct NewAttachment {
+    pub id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+impl NewAttachment {
+    pub fn key(&self) -> String {
+        format!("attachments/{}", self.id)
+    }
+}
+
+impl Attachment {
+    /// Largest file that can be attached, in bytes.
+    pub const MAX_SIZE: usize = 8 * 1024 * 1024;
+    pub const MAX_PER_MESSAGE: usize = 10;
+    pub const ALLOWED_CONTENT_TYPES: &[&str] = &[
+        "image/png",
+        "image/jpeg",
+        "image/gif",
+        "image/webp",
+        "video/mp4",
+        "video/webm",
+        "audio/mpeg",
+        "audio/ogg",
+        "application/pdf",
+        "application/zip",
+        "text/plain",
+    ];
+
+    pub async fn create<'e, E: PgExecutor<'e>>(
+        message_id: Uuid,
+        new_attachment: &NewAttachment,
+        executor: E,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO attachments(id, message_id, key, filename, content_type, size)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+            new_attachment.id,
+            message_id,
+            new_attachment.key(),
+            new_attachment.filename,
+            new_attachment.content_type,
+            new_attachment.data.len() as i32
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fetches an attachment of a message sent in the group.
+    pub async fn fetch(
+        id: Uuid,
+        group_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Option<Attachment>, Error> {
+        let attachment = sqlx::query_as!(
+            Attachment,
+            "SELECT attachments.id, key, filename, content_type, size FROM attachments
+                JOIN messages ON messages.id = attachments.message_id
+                WHERE attachments.id=$1 AND messages.group_id=$2",
+            id,
+            group_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(attachment)
+    }
+}
+
+/// File of a deleted attachment that is still waiting to be removed from storage.
+#[derive(Debug, Clone)]
+pub struct OrphanedFile {
+    pub key: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl OrphanedFile {
+    pub async fn fetch_all(limit: i64, pool: &PgPool) -> Result<Vec<OrphanedFile>, Error> {
+        let files = sqlx::query_as!(
+            OrphanedFile,
+            "SELECT * FROM orphaned_files ORDER BY created_at LIMIT $1",
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(files)
+    }
+
+    pub async fn delete(key: &str, pool: &PgPool) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM orphaned_files WHERE key=$1", key)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{prelude::FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Ban {
+    pub group_id: Uuid,
+    pub user_id: Uuid,
+    pub banned_by: Option<Uuid>,
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Ban {
+    pub async fn fetch_all(group_id: Uuid, pool: &PgPool) -> Result<Vec<Ban>, Error> {
+        let bans = sqlx::query_as!(
+            Ban,
+            "SELECT * FROM bans WHERE group_id=$1 ORDER BY created_at DESC",
+            group_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(bans)
+    }
+
+    /// Bans the user from the group, removing their membership if they have one.
+    /// Returns the ban and whether the user was a member.
+    pub async fn create(
+        group_id: Uuid,
+        user_id: Uuid,
+        banned_by: Uuid,
+        reason: Option<String>,
+        pool: &PgPool,
+    ) -> Result<(Ban, bool), Error> {
+        let mut transaction = pool.begin().await?;
+
+        let removed = sqlx::query!(
+            "DELETE FROM members WHERE user_id=$1 AND group_id=$2",
+            user_id,
+            group_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+            > 0;
+
+        let ban = sqlx::query_as!(
+            Ban,
+            "INSERT INTO bans(group_id, user_id, banned_by, reason) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (group_id, user_id) DO UPDATE SET banned_by = $3, reason = $4
+                RETURNING *",
+            group_id,
+            user_id,
+            banned_by,
+            reason
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok((ban, removed))
+    }
+
+    pub async fn delete(group_id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM bans WHERE group_id=$1 AND user_id=$2",
+            group_id,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    pub id: Uuid,
+    pub group_id: Uuid,
+
+    pub name: String,
+    pub topic: Option<String>,
+    pub position: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewChannel {
+    pub name: String,
+    pub topic: Option<String>,
+    pub position: i32,
+}
+
+impl Channel {
+    pub const DEFAULT_NAME: &'static str = "general";
+
+    /// Creates the channel every group starts out with.
+    pub async fn create_default<'e, E: PgExecutor<'e>>(
+        group_id: Uuid,
+        executor: E,
+    ) -> Result<Channel, Error> {
+        let channel = sqlx::query_as!(
+            Channel,
+            "INSERT INTO channels(group_id, name) VALUES ($1, $2) RETURNING *",
+            group_id,
+            Self::DEFAULT_NAME
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(channel)
+    }
+
+    pub async fn fetch(id: Uuid, group_id: Uuid, pool: &PgPool) -> Result<Option<Channel>, Error> {
+        let channel = sqlx::query_as!(
+            Channel,
+            "SELECT * FROM channels WHERE id=$1 AND group_id=$2",
+            id,
+            group_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(channel)
+    }
+
+    /// Fetches the topmost channel of the group, which messages sent to the
+    /// group itself end up in.
+    pub async fn fetch_default(group_id: Uuid, pool: &PgPool) -> Result<Channel, Error> {
+        let channel = sqlx::query_as!(
+            Channel,
+            "SELECT * FROM channels WHERE group_id=$1 ORDER BY position, created_at LIMIT 1",
+            group_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(channel)
+    }
+
+    pub async fn fetch_all(group_id: Uuid, pool: &PgPool) -> Result<Vec<Channel>, Error> {
+        let channels = sqlx::query_as!(
+            Channel,
+            "SELECT * FROM channels WHERE group_id=$1 ORDER BY position, created_at",
+            group_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(channels)
+    }
+
+    pub async fn create(
+        group_id: Uuid,
+        channel: &NewChannel,
+        pool: &PgPool,
+    ) -> Result<Channel, Error> {
+        let result = sqlx::query_as!(
+            Channel,
+            "INSERT INTO channels(group_id, name, topic, position) VALUES ($1, $2, $3, $4) RETURNING *",
+            group_id,
+            channel.name,
+            channel.topic,
+            channel.position
+        )
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Ok(channel) => Ok(channel),
+            Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+                Err(Error::CHANNEL_ALREADY_EXISTS)
+            }
+            Err(error) => Err(error)?,
+        }
+    }
+
+    pub async fn update(id: Uuid, channel: &NewChannel, pool: &PgPool) -> Result<Channel, Error> {
+        let result = sqlx::query_as!(
+            Channel,
+            "UPDATE channels SET name=$2, topic=$3, position=$4 WHERE id=$1 RETURNING *",
+            id,
+            channel.name,
+            channel.topic,
+            channel.position
+        )
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Ok(channel) => Ok(channel),
+            Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+                Err(Error::CHANNEL_ALREADY_EXISTS)
+            }
+            Err(error) => Err(error)?,
+        }
+    }
+
+    /// Deletes the channel along with its messages, unless it is the last one in its group.
+    pub async fn delete(&self, pool: &PgPool) -> Result<(), Error> {
+        let mut transaction = pool.begin().await?;
+
+        // Concurrent deletions in the group wait for each other, so they can't
+        // both see the other's channel and remove the last two.
+        sqlx::query!(
+            "SELECT id FROM groups WHERE id=$1 FOR NO KEY UPDATE",
+            self.group_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM channels WHERE id=$1
+                AND (SELECT COUNT(*) FROM channels WHERE group_id=$2) > 1",
+            self.id,
+            self.group_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::LAST_CHANNEL);
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::Error;
+
+use super::{Channel, Group, Member, Role, User};
+
+/// A one-to-one conversation, backed by a group flagged as direct so that
+/// messages, events and typing indicators work the same as in groups.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectMessage {
+    #[serde(flatten)]
+    pub group: Group,
+    pub recipient: User,
+}
+
+impl DirectMessage {
+    /// Finds the conversation between the two users, creating it if there is none.
+    /// Returns whether it was created.
+    pub async fn find_or_create(
+        user_id: Uuid,
+        recipient: User,
+        pool: &PgPool,
+    ) -> Result<(DirectMessage, bool), Error> {
+        let (first_user_id, second_user_id) = match user_id < recipient.id {
+            true => (user_id, recipient.id),
+            false => (recipient.id, user_id),
+        };
+
+        if let Some(group) = fetch_group(first_user_id, second_user_id, pool).await? {
+            return Ok((DirectMessage { group, recipient }, false));
+        }
+
+        let mut transaction = pool.begin().await?;
+
+        let group = sqlx::query_as!(
+            Group,
+            "INSERT INTO groups(name, owner_id, is_direct) VALUES ('', $1, true) RETURNING *",
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        let inserted = sqlx::query!(
+            "INSERT INTO direct_messages(group_id, first_user_id, second_user_id)
+                VALUES ($1, $2, $3) ON CONFLICT (first_user_id, second_user_id) DO NOTHING",
+            group.id,
+            first_user_id,
+            second_user_id
+        )
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected()
+            > 0;
+
+        // Someone else opened the conversation in the meantime.
+        if !inserted {
+            transaction.rollback().await?;
+
+            let group = fetch_group(first_user_id, second_user_id, pool)
+                .await?
+                .ok_or(Error::INTERNAL)?;
+
+            return Ok((DirectMessage { group, recipient }, false));
+        }
+
+        Role::create_built_in(group.id, &mut *transaction).await?;
+        Channel::create_default(group.id, &mut *transaction).await?;
+        Member::create(first_user_id, group.id, &mut *transaction).await?;
+        Member::create(second_user_id, group.id, &mut *transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok((DirectMessage { group, recipient }, true))
+    }
+
+    pub async fn fetch_all(user_id: Uuid, pool: &PgPool) -> Result<Vec<DirectMessage>, Error> {
+        let direct_messages = sqlx::query!(
+            r#"SELECT g.id, g.name, g.description, g.icon, g.owner_id, g.created_at, g.is_direct, g.edit_window,
+                u.id as "recipient_id", u.username, u.password_hash, u.token_version,
+                u.created_at as "recipient_created_at"
+            FROM direct_messages dm
+                JOIN groups g ON g.id = dm.group_id
+                JOIN users u ON u.id = CASE WHEN dm.first_user_id = $1
+                    THEN dm.second_user_id ELSE dm.first_user_id END
+            WHERE dm.first_user_id = $1 OR dm.second_user_id = $1
+            ORDER BY g.created_at DESC"#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| DirectMessage {
+            group: Group {
+                id: row.id,
+                name: row.name,
+                description: row.description,
+                icon: row.icon,
+                owner_id: row.owner_id,
+                created_at: row.created_at,
+                is_direct: row.is_direct,
+                edit_window: row.edit_window,
+            },
+            recipient: User {
+                id: row.recipient_id,
+                username: row.username,
+                password_hash: row.password_hash,
+                token_version: row.token_version,
+                created_at: row.recipient_created_at,
+            },
+        })
+        .collect();
+
+        Ok(direct_messages)
+    }
+}
+
+async fn fetch_group(
+    first_user_id: Uuid,
+    second_user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<Group>, Error> {
+    let group = sqlx::query_as!(
+        Group,
+        "SELECT g.* FROM direct_messages dm JOIN groups g ON g.id = dm.group_id
+            WHERE dm.first_user_id = $1 AND dm.second_user_id = $2",
+        first_user_id,
+        second_user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(group)
+}
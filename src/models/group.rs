@@ -3,7 +3,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
-use crate::{models::Member, Error};
+use crate::{
+    models::{Channel, GroupMember, Member, Role},
+    Error,
+};
 
 use super::User;
 
@@ -13,8 +16,14 @@ pub struct Group {
     pub id: Uuid,
 
     pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
     pub owner_id: Uuid,
     pub created_at: NaiveDateTime,
+    /// Whether this is a [`DirectMessage`](super::DirectMessage) between two users.
+    pub is_direct: bool,
+    /// Seconds after sending during which messages can be edited, without limit if unset.
+    pub edit_window: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,22 +33,93 @@ pub struct NewGroup {
     pub owner_id: Uuid,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatedGroup {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub edit_window: Option<i32>,
+}
+
 impl Group {
     pub async fn create(new_group: &NewGroup, pool: &PgPool) -> Result<(Group, Member), Error> {
+        let mut transaction = pool.begin().await?;
+
         let group = sqlx::query_as!(
             Group,
             "INSERT INTO groups(name, owner_id) VALUES ($1, $2) RETURNING *",
             new_group.name,
             new_group.owner_id
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *transaction)
         .await?;
 
-        let member = Member::create(new_group.owner_id, group.id, pool).await?;
+        Role::create_built_in(group.id, &mut *transaction).await?;
+        Channel::create_default(group.id, &mut *transaction).await?;
+        let member = Member::create(new_group.owner_id, group.id, &mut *transaction).await?;
+
+        transaction.commit().await?;
 
         Ok((group, member))
     }
 
+    pub async fn update(id: Uuid, group: &UpdatedGroup, pool: &PgPool) -> Result<Group, Error> {
+        let group = sqlx::query_as!(
+            Group,
+            "UPDATE groups SET name=$2, description=$3, icon=$4, edit_window=$5 WHERE id=$1 RETURNING *",
+            id,
+            group.name,
+            group.description,
+            group.icon,
+            group.edit_window
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// Hands the group over to `new_owner_id`, who must already be a member.
+    pub async fn transfer(&self, new_owner_id: Uuid, pool: &PgPool) -> Result<Group, Error> {
+        let mut transaction = pool.begin().await?;
+
+        let owner_id = sqlx::query_scalar!(
+            "SELECT owner_id FROM groups WHERE id=$1 FOR UPDATE",
+            self.id
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(Error::UNKNOWN_GROUP)?;
+        if owner_id != self.owner_id {
+            return Err(Error::INSUFFICIENT_PERMISSIONS);
+        }
+
+        let is_member = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM members WHERE user_id = $1 AND group_id = $2) as "exists!""#,
+            new_owner_id,
+            self.id
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+        if !is_member {
+            return Err(Error::UNKNOWN_MEMBER);
+        }
+
+        let group = sqlx::query_as!(
+            Group,
+            "UPDATE groups SET owner_id=$2 WHERE id=$1 RETURNING *",
+            self.id,
+            new_owner_id
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(group)
+    }
+
     pub async fn delete(group_id: Uuid, pool: &PgPool) -> Result<(), Error> {
         sqlx::query!("DELETE FROM groups WHERE id=$1", group_id)
             .execute(pool)
@@ -67,16 +147,28 @@ impl Group {
         Ok(groups)
     }
 
-    pub async fn fetch_members(group_id: Uuid, pool: &PgPool) -> Result<Vec<User>, Error> {
-        let members = sqlx::query_as!(
-            User,
-            "SELECT users.* FROM users
+    pub async fn fetch_members(group_id: Uuid, pool: &PgPool) -> Result<Vec<GroupMember>, Error> {
+        let members = sqlx::query!(
+            "SELECT users.*, members.role_id, members.joined_at FROM users
                 JOIN members ON user_id = users.id
             WHERE group_id = $1",
             group_id
         )
         .fetch_all(pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(|row| GroupMember {
+            user: User {
+                id: row.id,
+                username: row.username,
+                password_hash: row.password_hash,
+                token_version: row.token_version,
+                created_at: row.created_at,
+            },
+            role_id: row.role_id,
+            joined_at: row.joined_at,
+        })
+        .collect();
 
         Ok(members)
     }
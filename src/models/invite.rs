@@ -1,11 +1,14 @@
+use std::sync::Arc;
+
 use chrono::NaiveDateTime;
+use rand::{rngs::OsRng, Rng};
 use serde::Serialize;
 use sqlx::{prelude::FromRow, PgPool};
 use uuid::Uuid;
 
 use crate::Error;
 
-use super::Member;
+use super::{Group, Member};
 
 #[derive(Debug, Clone, Serialize, FromRow)]
 #[serde(rename_all = "camelCase")]
@@ -13,21 +16,137 @@ pub struct Invite {
     pub id: Uuid,
     pub code: String,
     pub uses: i32,
+    pub max_uses: Option<i32>,
 
     pub user_id: Uuid,
     pub group_id: Uuid,
     pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewInvite {
+    /// A vanity code to use instead of a generated one.
+    pub code: Option<String>,
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// Shape of generated invite codes.
+#[derive(Debug, Clone)]
+pub struct InviteCodes {
+    length: usize,
+    alphabet: Arc<[char]>,
+}
+
+impl InviteCodes {
+    pub const DEFAULT_LENGTH: usize = 10;
+    pub const DEFAULT_ALPHABET: &'static str =
+        "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+    pub const MIN_LENGTH: usize = 6;
+    pub const MAX_LENGTH: usize = 32;
+
+    /// Generating a code is retried this many times when it collides with an existing one.
+    const MAX_ATTEMPTS: usize = 5;
+
+    pub fn new(length: usize, alphabet: &str) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            (Self::MIN_LENGTH..=Self::MAX_LENGTH).contains(&length),
+            "invite code length must be between {} and {}",
+            Self::MIN_LENGTH,
+            Self::MAX_LENGTH
+        );
+
+        let mut alphabet = alphabet.chars().collect::<Vec<_>>();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+
+        anyhow::ensure!(
+            alphabet.len() >= 2 && alphabet.iter().all(char::is_ascii_alphanumeric),
+            "invite code alphabet must have at least two ASCII letters or digits"
+        );
+
+        Ok(Self {
+            length,
+            alphabet: alphabet.into(),
+        })
+    }
+
+    pub fn generate(&self) -> String {
+        (0..self.length)
+            .map(|_| self.alphabet[OsRng.gen_range(0..self.alphabet.len())])
+            .collect()
+    }
+}
+
+impl Default for InviteCodes {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LENGTH, Self::DEFAULT_ALPHABET)
+            .expect("default invite code format is valid")
+    }
+}
+
+/// What a user sees about an invite's group before joining it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitePreview {
+    pub code: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub group: Group,
+    pub member_count: i64,
 }
 
 impl Invite {
+    /// Fetches an invite that hasn't expired or run out of uses.
     pub async fn fetch_by_code(invite_code: &str, pool: &PgPool) -> Result<Option<Invite>, Error> {
-        let invite = sqlx::query_as!(Invite, "SELECT * FROM invites WHERE code=$1", invite_code)
-            .fetch_optional(pool)
-            .await?;
+        let invite = sqlx::query_as!(
+            Invite,
+            "SELECT * FROM invites WHERE code=$1
+                AND (max_uses IS NULL OR uses < max_uses)
+                AND (expires_at IS NULL OR expires_at > (now() AT TIME ZONE 'UTC'))",
+            invite_code
+        )
+        .fetch_optional(pool)
+        .await?;
 
         Ok(invite)
     }
 
+    pub async fn fetch_preview(
+        invite_code: &str,
+        pool: &PgPool,
+    ) -> Result<Option<InvitePreview>, Error> {
+        let preview = sqlx::query!(
+            r#"SELECT i.code, i.expires_at, g.*,
+                (SELECT COUNT(*) FROM members m WHERE m.group_id = g.id) as "member_count!"
+            FROM invites i JOIN groups g ON g.id = i.group_id
+            WHERE i.code=$1
+                AND (i.max_uses IS NULL OR i.uses < i.max_uses)
+                AND (i.expires_at IS NULL OR i.expires_at > (now() AT TIME ZONE 'UTC'))"#,
+            invite_code
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|row| InvitePreview {
+            code: row.code,
+            expires_at: row.expires_at,
+            group: Group {
+                id: row.id,
+                name: row.name,
+                description: row.description,
+                icon: row.icon,
+                owner_id: row.owner_id,
+                created_at: row.created_at,
+                is_direct: row.is_direct,
+                edit_window: row.edit_window,
+            },
+            member_count: row.member_count,
+        });
+
+        Ok(preview)
+    }
+
     pub async fn fetch_all(group_id: Uuid, pool: &PgPool) -> Result<Vec<Invite>, Error> {
         let invites = sqlx::query_as!(Invite, "SELECT * FROM invites WHERE group_id=$1", group_id)
             .fetch_all(pool)
@@ -36,29 +155,105 @@ impl Invite {
         Ok(invites)
     }
 
-    pub async fn create(group_id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<Invite, Error> {
+    pub async fn fetch(id: Uuid, group_id: Uuid, pool: &PgPool) -> Result<Option<Invite>, Error> {
         let invite = sqlx::query_as!(
             Invite,
-            "INSERT INTO invites(group_id, user_id) VALUES ($1, $2) RETURNING *",
-            group_id,
-            user_id
+            "SELECT * FROM invites WHERE id=$1 AND group_id=$2",
+            id,
+            group_id
         )
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await?;
 
         Ok(invite)
     }
 
-    pub async fn accept(&self, user_id: Uuid, pool: &PgPool) -> Result<(), Error> {
-        let mut transaction = pool.begin().await?;
+    /// Creates an invite under its vanity code, or under a freshly generated
+    /// one that is regenerated if it happens to be taken.
+    pub async fn create(
+        group_id: Uuid,
+        user_id: Uuid,
+        invite: &NewInvite,
+        codes: &InviteCodes,
+        pool: &PgPool,
+    ) -> Result<Invite, Error> {
+        let mut attempts = 0;
+
+        loop {
+            let code = match &invite.code {
+                Some(code) => code.clone(),
+                None => codes.generate(),
+            };
+
+            let result = sqlx::query_as!(
+                Invite,
+                "INSERT INTO invites(group_id, user_id, code, max_uses, expires_at)
+                    VALUES ($1, $2, $3, $4, $5) RETURNING *",
+                group_id,
+                user_id,
+                code,
+                invite.max_uses,
+                invite.expires_at
+            )
+            .fetch_one(pool)
+            .await;
+
+            match result {
+                Ok(invite) => return Ok(invite),
+                Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+                    if invite.code.is_some() {
+                        return Err(Error::INVITE_CODE_TAKEN);
+                    }
+
+                    attempts += 1;
+                    if attempts >= InviteCodes::MAX_ATTEMPTS {
+                        return Err(sqlx::Error::Database(error))?;
+                    }
+                }
+                Err(error) => return Err(error)?,
+            }
+        }
+    }
 
-        sqlx::query!("UPDATE invites SET uses = uses + 1 WHERE id = $1", self.id)
-            .execute(&mut *transaction)
+    pub async fn delete(&self, pool: &PgPool) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM invites WHERE id=$1", self.id)
+            .execute(pool)
             .await?;
-        Member::create(user_id, self.group_id, &mut *transaction).await?;
+
+        Ok(())
+    }
+
+    pub async fn accept(&self, user_id: Uuid, pool: &PgPool) -> Result<Member, Error> {
+        let mut transaction = pool.begin().await?;
+
+        let banned = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM bans WHERE group_id=$1 AND user_id=$2) as "banned!""#,
+            self.group_id,
+            user_id
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+        if banned {
+            return Err(Error::BANNED);
+        }
+
+        // Claiming a use re-checks the limits under the row lock, so concurrent
+        // accepts can't push an invite past them.
+        sqlx::query!(
+            "UPDATE invites SET uses = uses + 1 WHERE id = $1
+                AND (max_uses IS NULL OR uses < max_uses)
+                AND (expires_at IS NULL OR expires_at > (now() AT TIME ZONE 'UTC'))
+                RETURNING id",
+            self.id
+        )
+        .fetch_optional(&mut *transaction)
+        .await?
+        .ok_or(Error::UNKNOWN_INVITE)?;
+
+        let member = Member::create(user_id, self.group_id, &mut *transaction).await?;
 
         transaction.commit().await?;
 
-        Ok(())
+        Ok(member)
     }
 }
@@ -1,16 +1,29 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::{Executor, FromRow, Postgres};
+use sqlx::{Executor, FromRow, PgPool, Postgres};
 use uuid::Uuid;
 
 use crate::Error;
 
+use super::User;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Member {
     pub user_id: Uuid,
     pub group_id: Uuid,
     pub joined_at: NaiveDateTime,
+    pub role_id: Uuid,
+}
+
+/// A member of a group along with their user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMember {
+    #[serde(flatten)]
+    pub user: User,
+    pub role_id: Uuid,
+    pub joined_at: NaiveDateTime,
 }
 
 impl Member {
@@ -19,13 +32,64 @@ impl Member {
         group_id: Uuid,
         executor: E,
     ) -> Result<Member, Error> {
-        let member = sqlx::query_as!(
+        let result = sqlx::query_as!(
             Member,
-            "INSERT INTO members(user_id, group_id) VALUES ($1, $2) RETURNING *",
+            "INSERT INTO members(user_id, group_id, role_id)
+                VALUES ($1, $2, (SELECT id FROM roles WHERE group_id = $2 AND is_default))
+                RETURNING *",
             user_id,
             group_id
         )
         .fetch_one(executor)
+        .await;
+
+        match result {
+            Ok(member) => Ok(member),
+            Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+                Err(Error::ALREADY_MEMBER)
+            }
+            Err(error) => Err(error)?,
+        }
+    }
+
+    pub async fn fetch(
+        user_id: Uuid,
+        group_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Option<Member>, Error> {
+        let member = sqlx::query_as!(
+            Member,
+            "SELECT * FROM members WHERE user_id=$1 AND group_id=$2",
+            user_id,
+            group_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(member)
+    }
+
+    pub async fn delete(user_id: Uuid, group_id: Uuid, pool: &PgPool) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM members WHERE user_id=$1 AND group_id=$2",
+            user_id,
+            group_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_role(&self, role_id: Uuid, pool: &PgPool) -> Result<Member, Error> {
+        let member = sqlx::query_as!(
+            Member,
+            "UPDATE members SET role_id=$3 WHERE user_id=$1 AND group_id=$2 RETURNING *",
+            self.user_id,
+            self.group_id,
+            role_id
+        )
+        .fetch_one(pool)
         .await?;
 
         Ok(member)
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+/// A user mentioned by a message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MentionedUser {
+    pub id: Uuid,
+    pub username: String,
+}
+
+pub struct Mention;
+
+impl Mention {
+    /// Finds the members of a group with the given usernames, leaving out
+    /// anyone who isn't a member.
+    pub async fn resolve(
+        usernames: &[String],
+        group_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<Vec<MentionedUser>, Error> {
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let users = sqlx::query_as!(
+            MentionedUser,
+            "SELECT users.id, users.username FROM users
+                JOIN members ON members.user_id = users.id AND members.group_id = $2
+            WHERE users.username = ANY($1)",
+            usernames,
+            group_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    pub async fn create_all<'e, E: PgExecutor<'e>>(
+        message_id: Uuid,
+        user_ids: &[Uuid],
+        executor: E,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "INSERT INTO mentions(message_id, user_id) SELECT $1, unnest($2::uuid[])",
+            message_id,
+            user_ids
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_all<'e, E: PgExecutor<'e>>(
+        message_id: Uuid,
+        executor: E,
+    ) -> Result<(), Error> {
+        sqlx::query!("DELETE FROM mentions WHERE message_id = $1", message_id)
+            .execute(executor)
+            .await?;
+
+        Ok(())
+    }
+}
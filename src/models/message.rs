@@ -1,11 +1,13 @@
-use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgExecutor, PgPool};
+use sqlx::{types::Json, FromRow, PgExecutor, PgPool};
 use uuid::Uuid;
 
 use crate::Error;
 
-use super::{Group, User};
+use super::{Attachment, Group, Mention, MentionedUser, NewAttachment, ReactionCount, User};
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 #[serde(rename_all = "camelCase")]
@@ -14,33 +16,72 @@ pub struct Message {
 
     pub user_id: Uuid,
     pub group_id: Uuid,
+    pub channel_id: Uuid,
     pub content: String,
 
+    /// Message quoted inline by this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<Uuid>,
+    /// First message of the thread this one was posted in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_root: Option<Uuid>,
+    /// Number of messages in the thread started by this one.
+    pub reply_count: i64,
+    pub reactions: Json<Vec<ReactionCount>>,
+    pub attachments: Json<Vec<Attachment>>,
+    pub mentions: Json<Vec<MentionedUser>>,
+    /// Whether `@everyone` was mentioned by someone allowed to.
+    pub mentions_everyone: bool,
+
     pub created_at: NaiveDateTime,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<NaiveDateTime>,
 }
 
+/// Position in a channel or thread to list messages from.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum MessageCursor {
+    /// The most recent messages.
+    #[default]
+    Latest,
+    /// Messages sent before this one.
+    Before(Uuid),
+    /// Messages sent after this one.
+    After(Uuid),
+    /// This message along with the ones sent right before and after it.
+    Around(Uuid),
+    /// Messages sent at or after this time.
+    Since(NaiveDateTime),
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct MessageQuery {
     pub limit: i64,
-    pub group_id: Uuid,
-    pub before: Option<Uuid>,
+    pub channel_id: Uuid,
+    /// Lists the messages of this thread instead of the channel's own.
+    pub thread_root: Option<Uuid>,
+    pub cursor: MessageCursor,
 }
 
 impl MessageQuery {
     const MAX_MESSAGES_PER_QUERY: i64 = 100;
 
-    pub fn new(group_id: Uuid) -> Self {
+    pub fn new(channel_id: Uuid) -> Self {
         Self {
-            group_id,
-            before: None,
+            channel_id,
+            thread_root: None,
+            cursor: MessageCursor::Latest,
             limit: 50,
         }
     }
 
-    pub fn before(mut self, id: Uuid) -> Self {
-        self.before = Some(id);
+    pub fn thread(mut self, root: Uuid) -> Self {
+        self.thread_root = Some(root);
+        self
+    }
+
+    pub fn cursor(mut self, cursor: MessageCursor) -> Self {
+        self.cursor = cursor;
         self
     }
 
@@ -50,38 +91,107 @@ impl MessageQuery {
     }
 }
 
+/// Full-text search over the messages of a set of groups.
+#[derive(Debug, Clone)]
+pub struct MessageSearch {
+    pub query: String,
+    pub group_ids: Vec<Uuid>,
+    pub channel_id: Option<Uuid>,
+    pub author_id: Option<Uuid>,
+    pub after: Option<NaiveDateTime>,
+    pub before: Option<NaiveDateTime>,
+    pub has_link: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub message: Message,
+    pub rank: f32,
+    /// HTML-escaped content with the matching words wrapped in `<mark>` tags.
+    pub highlight: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct NewMessage {
     pub user_id: Uuid,
     pub group_id: Uuid,
+    pub channel_id: Uuid,
+    pub content: String,
+    pub reply_to: Option<Uuid>,
+    pub thread_root: Option<Uuid>,
+    pub attachments: Vec<NewAttachment>,
+    pub mentions: Vec<Uuid>,
+    pub mentions_everyone: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdatedMessage {
     pub content: String,
+    pub mentions: Vec<Uuid>,
+    pub mentions_everyone: bool,
 }
 
 impl Message {
     pub async fn create(new_message: &NewMessage, pool: &PgPool) -> Result<Message, Error> {
-        let message = sqlx::query_as!(
-            Message,
-            "INSERT INTO messages(user_id, group_id, content) VALUES ($1, $2, $3) RETURNING *",
+        let mut transaction = pool.begin().await?;
+
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO messages(user_id, group_id, channel_id, content, reply_to, thread_root, mentions_everyone)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id"#,
             new_message.user_id,
             new_message.group_id,
-            new_message.content
+            new_message.channel_id,
+            new_message.content,
+            new_message.reply_to,
+            new_message.thread_root,
+            new_message.mentions_everyone
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *transaction)
         .await?;
 
+        for attachment in &new_message.attachments {
+            Attachment::create(id, attachment, &mut *transaction).await?;
+        }
+        Mention::create_all(id, &new_message.mentions, &mut *transaction).await?;
+
+        let message = Self::fetch(id, &mut *transaction)
+            .await?
+            .ok_or(Error::UNKNOWN_MESSAGE)?;
+
+        transaction.commit().await?;
+
         Ok(message)
     }
 
-    pub async fn edit(id: Uuid, content: &str, pool: &PgPool) -> Result<Message, Error> {
-        let message = sqlx::query_as!(
-            Message,
-            "UPDATE messages SET content=$1 WHERE id=$2 RETURNING *",
-            content,
+    pub async fn edit(
+        id: Uuid,
+        updated_message: &UpdatedMessage,
+        pool: &PgPool,
+    ) -> Result<Message, Error> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query!(
+            "UPDATE messages SET content=$1, mentions_everyone=$2 WHERE id=$3",
+            updated_message.content,
+            updated_message.mentions_everyone,
             id
         )
-        .fetch_one(pool)
+        .execute(&mut *transaction)
         .await?;
 
+        Mention::delete_all(id, &mut *transaction).await?;
+        Mention::create_all(id, &updated_message.mentions, &mut *transaction).await?;
+
+        let message = Self::fetch(id, &mut *transaction)
+            .await?
+            .ok_or(Error::UNKNOWN_MESSAGE)?;
+
+        transaction.commit().await?;
+
         Ok(message)
     }
 
@@ -89,29 +199,158 @@ impl Message {
         message_id: Uuid,
         executor: E,
     ) -> Result<Option<Message>, Error> {
-        let message = sqlx::query_as!(Message, "SELECT * FROM messages WHERE id=$1", message_id)
-            .fetch_optional(executor)
-            .await?;
+        let message = sqlx::query_as!(
+            Message,
+            r#"SELECT id AS "id!", user_id AS "user_id!", group_id AS "group_id!", channel_id AS "channel_id!",
+            content AS "content!", reply_to, thread_root, reply_count AS "reply_count!",
+            reactions AS "reactions!: Json<Vec<ReactionCount>>",
+            attachments AS "attachments!: Json<Vec<Attachment>>",
+            mentions AS "mentions!: Json<Vec<MentionedUser>>",
+            mentions_everyone AS "mentions_everyone!", created_at AS "created_at!", updated_at
+            FROM message_details WHERE id=$1"#,
+            message_id
+        )
+        .fetch_optional(executor)
+        .await?;
 
         Ok(message)
     }
 
+    /// Lists messages in chronological order, paging on `(created_at, id)` so
+    /// messages sent at the same time are neither skipped nor repeated.
     pub async fn fetch_all(query: &MessageQuery, pool: &PgPool) -> Result<Vec<Message>, Error> {
+        let limit = query.limit.clamp(1, MessageQuery::MAX_MESSAGES_PER_QUERY);
+
+        let messages = match query.cursor {
+            MessageCursor::Latest => {
+                let mut messages = Self::fetch_older(query, None, limit, pool).await?;
+                messages.reverse();
+                messages
+            }
+            MessageCursor::Before(id) => {
+                let anchor = Self::fetch_anchor(id, query, pool).await?;
+                let key = (anchor.created_at, anchor.id);
+
+                let mut messages = Self::fetch_older(query, Some(key), limit, pool).await?;
+                messages.reverse();
+                messages
+            }
+            MessageCursor::After(id) => {
+                let anchor = Self::fetch_anchor(id, query, pool).await?;
+                let key = (anchor.created_at, anchor.id);
+
+                Self::fetch_newer(query, key, limit, pool).await?
+            }
+            MessageCursor::Around(id) => {
+                let anchor = Self::fetch_anchor(id, query, pool).await?;
+                let key = (anchor.created_at, anchor.id);
+                let older_limit = (limit - 1) / 2;
+                let newer_limit = limit - 1 - older_limit;
+
+                let mut messages = Self::fetch_older(query, Some(key), older_limit, pool).await?;
+                messages.reverse();
+                messages.push(anchor);
+                messages.extend(Self::fetch_newer(query, key, newer_limit, pool).await?);
+                messages
+            }
+            // No message has the nil ID, so this includes every message sent at `since`.
+            MessageCursor::Since(since) => {
+                Self::fetch_newer(query, (since, Uuid::nil()), limit, pool).await?
+            }
+        };
+
+        Ok(messages)
+    }
+
+    /// Fetches the message a cursor points to, which must be listed by the query.
+    async fn fetch_anchor(id: Uuid, query: &MessageQuery, pool: &PgPool) -> Result<Message, Error> {
+        Self::fetch(id, pool)
+            .await?
+            .filter(|message| {
+                message.channel_id == query.channel_id && message.thread_root == query.thread_root
+            })
+            .ok_or(Error::UNKNOWN_MESSAGE)
+    }
+
+    /// Fetches the messages sorting before `key`, newest first.
+    async fn fetch_older(
+        query: &MessageQuery,
+        key: Option<(NaiveDateTime, Uuid)>,
+        limit: i64,
+        pool: &PgPool,
+    ) -> Result<Vec<Message>, Error> {
+        let (created_at, id) = key.unzip();
+        let messages = sqlx::query_as!(
+            Message,
+            r#"SELECT id AS "id!", user_id AS "user_id!", group_id AS "group_id!", channel_id AS "channel_id!",
+            content AS "content!", reply_to, thread_root, reply_count AS "reply_count!",
+            reactions AS "reactions!: Json<Vec<ReactionCount>>",
+            attachments AS "attachments!: Json<Vec<Attachment>>",
+            mentions AS "mentions!: Json<Vec<MentionedUser>>",
+            mentions_everyone AS "mentions_everyone!", created_at AS "created_at!", updated_at
+            FROM message_details
+            WHERE channel_id = $1
+                AND thread_root IS NOT DISTINCT FROM $2
+                AND ($3::timestamp IS NULL OR (created_at, id) < ($3, $4))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5"#,
+            query.channel_id,
+            query.thread_root,
+            created_at,
+            id,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// Fetches the messages sorting after `key`, oldest first.
+    async fn fetch_newer(
+        query: &MessageQuery,
+        (created_at, id): (NaiveDateTime, Uuid),
+        limit: i64,
+        pool: &PgPool,
+    ) -> Result<Vec<Message>, Error> {
+        let messages = sqlx::query_as!(
+            Message,
+            r#"SELECT id AS "id!", user_id AS "user_id!", group_id AS "group_id!", channel_id AS "channel_id!",
+            content AS "content!", reply_to, thread_root, reply_count AS "reply_count!",
+            reactions AS "reactions!: Json<Vec<ReactionCount>>",
+            attachments AS "attachments!: Json<Vec<Attachment>>",
+            mentions AS "mentions!: Json<Vec<MentionedUser>>",
+            mentions_everyone AS "mentions_everyone!", created_at AS "created_at!", updated_at
+            FROM message_details
+            WHERE channel_id = $1
+                AND thread_root IS NOT DISTINCT FROM $2
+                AND (created_at, id) > ($3, $4)
+            ORDER BY created_at ASC, id ASC
+            LIMIT $5"#,
+            query.channel_id,
+            query.thread_root,
+            created_at,
+            id,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// Fetches the messages with the given ids, in no particular order.
+    pub async fn fetch_many(ids: &[Uuid], pool: &PgPool) -> Result<Vec<Message>, Error> {
         let messages = sqlx::query_as!(
             Message,
-            r#"SELECT * FROM (
-                SELECT * FROM messages
-                WHERE group_id = $1
-                    AND ($2::uuid IS NULL OR created_at < (SELECT created_at FROM messages WHERE id = $2))
-                ORDER BY
-                    CASE WHEN $2::uuid IS NULL THEN created_at END DESC,
-                    CASE WHEN $2::uuid IS NOT NULL THEN created_at END ASC
-                LIMIT $3
-            ) AS sub
-            ORDER BY created_at ASC"#,
-            query.group_id,
-            query.before,
-            query.limit
+            r#"SELECT id AS "id!", user_id AS "user_id!", group_id AS "group_id!", channel_id AS "channel_id!",
+            content AS "content!", reply_to, thread_root, reply_count AS "reply_count!",
+            reactions AS "reactions!: Json<Vec<ReactionCount>>",
+            attachments AS "attachments!: Json<Vec<Attachment>>",
+            mentions AS "mentions!: Json<Vec<MentionedUser>>",
+            mentions_everyone AS "mentions_everyone!", created_at AS "created_at!", updated_at
+            FROM message_details WHERE id = ANY($1)"#,
+            ids
         )
         .fetch_all(pool)
         .await?;
@@ -119,6 +358,62 @@ impl Message {
         Ok(messages)
     }
 
+    /// Finds the messages matching a web search style query, best matches first.
+    pub async fn search(search: &MessageSearch, pool: &PgPool) -> Result<Vec<SearchResult>, Error> {
+        let hits = sqlx::query!(
+            r#"SELECT id,
+            ts_rank(search_vector, query) AS "rank!",
+            ts_headline(
+                'english',
+                translate(content, chr(2) || chr(3), ''),
+                query,
+                'StartSel=' || chr(2) || ', StopSel=' || chr(3) || ', HighlightAll=true'
+            ) AS "highlight!"
+            FROM messages, websearch_to_tsquery('english', $1) AS query
+            WHERE search_vector @@ query
+                AND group_id = ANY($2)
+                AND ($3::uuid IS NULL OR channel_id = $3)
+                AND ($4::uuid IS NULL OR user_id = $4)
+                AND ($5::timestamp IS NULL OR created_at >= $5)
+                AND ($6::timestamp IS NULL OR created_at < $6)
+                AND ($7::bool IS NULL OR (content ~* 'https?://') = $7)
+            ORDER BY ts_rank(search_vector, query) DESC, created_at DESC
+            LIMIT $8 OFFSET $9"#,
+            search.query,
+            &search.group_ids,
+            search.channel_id,
+            search.author_id,
+            search.after,
+            search.before,
+            search.has_link,
+            search.limit,
+            search.offset
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let ids = hits.iter().map(|hit| hit.id).collect::<Vec<_>>();
+        let mut messages = Self::fetch_many(&ids, pool)
+            .await?
+            .into_iter()
+            .map(|message| (message.id, message))
+            .collect::<HashMap<_, _>>();
+
+        // Messages deleted in between are left out.
+        let results = hits
+            .into_iter()
+            .filter_map(|hit| {
+                Some(SearchResult {
+                    message: messages.remove(&hit.id)?,
+                    rank: hit.rank,
+                    highlight: mark_highlight(&hit.highlight),
+                })
+            })
+            .collect();
+
+        Ok(results)
+    }
+
     pub async fn delete(message_id: Uuid, pool: &PgPool) -> Result<(), Error> {
         sqlx::query!("DELETE FROM messages WHERE id=$1", message_id)
             .execute(pool)
@@ -147,3 +442,83 @@ pub async fn fetch_with_group_check(
 pub fn can_modify_message(user: &User, message: &Message) -> bool {
     user.id == message.user_id
 }
+
+/// Whether the edit window of the group, if it has one, is still open for a message.
+pub fn is_within_edit_window(group: &Group, message: &Message) -> bool {
+    edit_window_open(
+        group.edit_window,
+        message.created_at,
+        Utc::now().naive_utc(),
+    )
+}
+
+fn edit_window_open(window: Option<i32>, sent_at: NaiveDateTime, now: NaiveDateTime) -> bool {
+    window.is_none_or(|window| now - sent_at <= TimeDelta::seconds(window.into()))
+}
+
+/// Escapes a headline delimited with STX and ETX and marks its matches with `<mark>`.
+///
+/// Highlighting raw content and escaping afterwards keeps ts_headline from
+/// matching inside entities or cutting them in half.
+fn mark_highlight(headline: &str) -> String {
+    let mut marked = String::with_capacity(headline.len());
+
+    for char in headline.chars() {
+        match char {
+            '\u{2}' => marked.push_str("<mark>"),
+            '\u{3}' => marked.push_str("</mark>"),
+            '&' => marked.push_str("&amp;"),
+            '<' => marked.push_str("&lt;"),
+            '>' => marked.push_str("&gt;"),
+            '"' => marked.push_str("&quot;"),
+            '\'' => marked.push_str("&#39;"),
+            char => marked.push(char),
+        }
+    }
+
+    marked
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta};
+
+    use super::{edit_window_open, mark_highlight};
+
+    #[test]
+    fn marks_matches() {
+        assert_eq!(
+            mark_highlight("say \u{2}hello\u{3} to \u{2}everyone\u{3}"),
+            "say <mark>hello</mark> to <mark>everyone</mark>"
+        );
+    }
+
+    #[test]
+    fn escapes_content() {
+        assert_eq!(
+            mark_highlight("\u{2}amp\u{3} & <b>\"it's\"</b>"),
+            "<mark>amp</mark> &amp; &lt;b&gt;&quot;it&#39;s&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn closes_edit_window_after_window() {
+        let sent_at = DateTime::from_timestamp(1_700_000_000, 0)
+            .unwrap()
+            .naive_utc();
+        let window = TimeDelta::seconds(900);
+
+        assert!(edit_window_open(Some(900), sent_at, sent_at));
+        assert!(edit_window_open(Some(900), sent_at, sent_at + window));
+        assert!(!edit_window_open(
+            Some(900),
+            sent_at,
+            sent_at + window + TimeDelta::milliseconds(1)
+        ));
+        assert!(edit_window_open(
+            None,
+            sent_at,
+            sent_at + TimeDelta::days(365)
+        ));
+    }
+}
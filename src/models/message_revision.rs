@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+/// Content a message had before it was edited. Revisions are recorded by a
+/// trigger whenever the content of a message changes.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRevision {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub replaced_at: NaiveDateTime,
+}
+
+impl MessageRevision {
+    /// Fetches the previous versions of a message, oldest first.
+    pub async fn fetch_all(message_id: Uuid, pool: &PgPool) -> Result<Vec<MessageRevision>, Error> {
+        let revisions = sqlx::query_as!(
+            MessageRevision,
+            "SELECT * FROM message_revisions WHERE message_id=$1 ORDER BY created_at",
+            message_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(revisions)
+    }
+}
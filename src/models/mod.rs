@@ -1,9 +1,31 @@
 pub mod user;
 pub mod group;
 pub mod member;
+pub mod channel;
 pub mod message;
+pub mod message_revision;
 pub mod invite;
+pub mod role;
+pub mod session;
+pub mod totp;
+pub mod ban;
+pub mod attachment;
+pub mod mention;
+pub mod reaction;
+pub mod read_state;
+pub mod direct_message;
 
 pub use user::User;
-pub use group::{Group, NewGroup};
-pub use member::Member;
\ No newline at end of file
+pub use ban::Ban;
+pub use attachment::{Attachment, NewAttachment};
+pub use channel::{Channel, NewChannel};
+pub use direct_message::DirectMessage;
+pub use group::{Group, NewGroup, UpdatedGroup};
+pub use member::{GroupMember, Member};
+pub use message_revision::MessageRevision;
+pub use mention::{Mention, MentionedUser};
+pub use reaction::{Reaction, ReactionCount};
+pub use read_state::{GroupWithReadStates, ReadState};
+pub use role::{Permission, Role};
+pub use session::{NewSession, Session};
+pub use totp::Totp;
\ No newline at end of file
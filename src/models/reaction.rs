@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// Number of users that reacted to a message with an emoji.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+}
+
+pub struct Reaction;
+
+impl Reaction {
+    /// Number of different emojis a message can be reacted with.
+    pub const MAX_EMOJIS_PER_MESSAGE: i64 = 20;
+
+    /// Returns whether the reaction was added, which is not the case if the user
+    /// already reacted with this emoji.
+    pub async fn create(
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: &str,
+        pool: &PgPool,
+    ) -> Result<bool, Error> {
+        let mut transaction = pool.begin().await?;
+
+        // Reactions to the same message wait for each other so the cap holds.
+        sqlx::query!(
+            "SELECT id FROM messages WHERE id=$1 FOR NO KEY UPDATE",
+            message_id
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+
+        let other_emojis = sqlx::query_scalar!(
+            r#"SELECT COUNT(DISTINCT emoji) AS "count!" FROM reactions
+                WHERE message_id=$1 AND emoji<>$2"#,
+            message_id,
+            emoji
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if other_emojis >= Self::MAX_EMOJIS_PER_MESSAGE {
+            return Err(Error::TOO_MANY_REACTIONS);
+        }
+
+        let result = sqlx::query!(
+            "INSERT INTO reactions(message_id, user_id, emoji) VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING",
+            message_id,
+            user_id,
+            emoji
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: &str,
+        pool: &PgPool,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM reactions WHERE message_id=$1 AND user_id=$2 AND emoji=$3",
+            message_id,
+            user_id,
+            emoji
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::Error;
+
+use super::{message::Message, Group};
+
+/// What a user has read in a channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadState {
+    pub group_id: Uuid,
+    pub channel_id: Uuid,
+    pub last_read_message_id: Option<Uuid>,
+    /// Messages sent by others since the last read one, or since the user
+    /// joined if they never read the channel. Thread replies are not counted.
+    pub unread_count: i64,
+    /// Unread messages, thread replies included, that mention the user.
+    pub mention_count: i64,
+}
+
+/// A group along with what the user has read in each of its channels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupWithReadStates {
+    #[serde(flatten)]
+    pub group: Group,
+    pub unread_count: i64,
+    pub mention_count: i64,
+    pub read_states: Vec<ReadState>,
+}
+
+impl GroupWithReadStates {
+    pub fn new(group: Group, read_states: Vec<ReadState>) -> Self {
+        Self {
+            unread_count: read_states.iter().map(|state| state.unread_count).sum(),
+            mention_count: read_states.iter().map(|state| state.mention_count).sum(),
+            group,
+            read_states,
+        }
+    }
+}
+
+impl ReadState {
+    /// Marks the channel of a message as read up to and including it. Returns
+    /// whether anything changed, which is not the case if a later message was
+    /// already read.
+    pub async fn ack(user_id: Uuid, message: &Message, pool: &PgPool) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "INSERT INTO read_states(user_id, group_id, channel_id, last_read_message_id, last_read_at)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (user_id, channel_id) DO UPDATE
+                    SET last_read_message_id = EXCLUDED.last_read_message_id,
+                        last_read_at = EXCLUDED.last_read_at,
+                        updated_at = (now() AT TIME ZONE 'UTC')
+                    WHERE (read_states.last_read_at, read_states.last_read_message_id)
+                        < (EXCLUDED.last_read_at, EXCLUDED.last_read_message_id)",
+            user_id,
+            message.group_id,
+            message.channel_id,
+            message.id,
+            message.created_at
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn fetch(user_id: Uuid, channel_id: Uuid, pool: &PgPool) -> Result<ReadState, Error> {
+        Self::fetch_by_channel(user_id, Some(channel_id), pool)
+            .await?
+            .pop()
+            .ok_or(Error::UNKNOWN_CHANNEL)
+    }
+
+    /// Fetches the read state of every channel of the groups the user is a member of.
+    pub async fn fetch_all(user_id: Uuid, pool: &PgPool) -> Result<Vec<ReadState>, Error> {
+        Self::fetch_by_channel(user_id, None, pool).await
+    }
+
+    async fn fetch_by_channel(
+        user_id: Uuid,
+        channel_id: Option<Uuid>,
+        pool: &PgPool,
+    ) -> Result<Vec<ReadState>, Error> {
+        let read_states = sqlx::query_as!(
+            ReadState,
+            r#"SELECT channels.group_id, channels.id AS channel_id,
+            read_states.last_read_message_id AS "last_read_message_id?",
+            (
+                SELECT COUNT(*) FROM messages
+                WHERE messages.channel_id = channels.id
+                    AND messages.thread_root IS NULL
+                    AND messages.user_id <> members.user_id
+                    AND (messages.created_at, messages.id) > (
+                        COALESCE(read_states.last_read_at, members.joined_at),
+                        COALESCE(read_states.last_read_message_id, '00000000-0000-0000-0000-000000000000')
+                    )
+            ) AS "unread_count!",
+            (
+                SELECT COUNT(*) FROM messages
+                WHERE messages.channel_id = channels.id
+                    AND messages.user_id <> members.user_id
+                    AND (messages.created_at, messages.id) > (
+                        COALESCE(read_states.last_read_at, members.joined_at),
+                        COALESCE(read_states.last_read_message_id, '00000000-0000-0000-0000-000000000000')
+                    )
+                    AND (messages.mentions_everyone OR EXISTS(
+                        SELECT 1 FROM mentions
+                        WHERE mentions.message_id = messages.id AND mentions.user_id = members.user_id
+                    ))
+            ) AS "mention_count!"
+            FROM members
+            JOIN channels ON channels.group_id = members.group_id
+            LEFT JOIN read_states ON read_states.user_id = members.user_id
+                AND read_states.channel_id = channels.id
+            WHERE members.user_id = $1 AND ($2::uuid IS NULL OR channels.id = $2)
+            ORDER BY channels.group_id, channels.position, channels.created_at"#,
+            user_id,
+            channel_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(read_states)
+    }
+}
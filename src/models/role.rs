@@ -0,0 +1,266 @@
+use bitflags::bitflags;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{prelude::FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+use super::Group;
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Permission: i64 {
+        const SEND_MESSAGES = 1 << 0;
+        const CREATE_INVITES = 1 << 1;
+        const MANAGE_INVITES = 1 << 2;
+        const MANAGE_MESSAGES = 1 << 3;
+        const KICK_MEMBERS = 1 << 4;
+        const BAN_MEMBERS = 1 << 5;
+        const MANAGE_ROLES = 1 << 6;
+        const MANAGE_GROUP = 1 << 7;
+        /// Not part of any built-in role, so only the owner has it unless they grant it.
+        const DELETE_GROUP = 1 << 8;
+        /// Lets `@everyone` notify every member instead of staying plain text.
+        const MENTION_EVERYONE = 1 << 9;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Role {
+    pub id: Uuid,
+    pub group_id: Uuid,
+
+    pub name: String,
+    pub permissions: i64,
+    /// Roles can only be managed by members whose role is positioned higher.
+    pub position: i32,
+    /// The role given to new members, which can't be deleted.
+    pub is_default: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewRole {
+    pub name: String,
+    pub permissions: Permission,
+    pub position: i32,
+}
+
+impl Role {
+    /// Roles every group starts with, the first of which is the default one.
+    pub const BUILT_IN: [(&'static str, Permission, i32); 3] = [
+        ("member", Permission::SEND_MESSAGES, 0),
+        (
+            "moderator",
+            Permission::SEND_MESSAGES
+                .union(Permission::CREATE_INVITES)
+                .union(Permission::MANAGE_INVITES)
+                .union(Permission::MANAGE_MESSAGES)
+                .union(Permission::KICK_MEMBERS)
+                .union(Permission::MENTION_EVERYONE),
+            1,
+        ),
+        (
+            "admin",
+            Permission::all().difference(Permission::DELETE_GROUP),
+            2,
+        ),
+    ];
+
+    pub fn permissions(&self) -> Permission {
+        Permission::from_bits_truncate(self.permissions)
+    }
+
+    /// Creates the built-in roles of a new group.
+    pub async fn create_built_in<'e, E: PgExecutor<'e>>(
+        group_id: Uuid,
+        executor: E,
+    ) -> Result<(), Error> {
+        let (names, permissions, positions): (Vec<_>, Vec<_>, Vec<_>) = Self::BUILT_IN
+            .iter()
+            .map(|(name, permissions, position)| (name.to_string(), permissions.bits(), *position))
+            .collect();
+
+        sqlx::query!(
+            "INSERT INTO roles (group_id, name, permissions, position, is_default)
+                SELECT $1, name, permissions, position, ordinality = 1
+                FROM UNNEST($2::varchar[], $3::bigint[], $4::integer[]) WITH ORDINALITY
+                    AS built_in(name, permissions, position, ordinality)",
+            group_id,
+            &names,
+            &permissions,
+            &positions
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch(id: Uuid, group_id: Uuid, pool: &PgPool) -> Result<Option<Role>, Error> {
+        let role = sqlx::query_as!(
+            Role,
+            "SELECT * FROM roles WHERE id=$1 AND group_id=$2",
+            id,
+            group_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    pub async fn fetch_all(group_id: Uuid, pool: &PgPool) -> Result<Vec<Role>, Error> {
+        let roles = sqlx::query_as!(
+            Role,
+            "SELECT * FROM roles WHERE group_id=$1 ORDER BY position DESC, created_at",
+            group_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn create(group_id: Uuid, new_role: &NewRole, pool: &PgPool) -> Result<Role, Error> {
+        let role = sqlx::query_as!(
+            Role,
+            "INSERT INTO roles (group_id, name, permissions, position) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (group_id, name) DO NOTHING
+                RETURNING *",
+            group_id,
+            new_role.name,
+            new_role.permissions.bits(),
+            new_role.position
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(Error::ROLE_ALREADY_EXISTS)?;
+
+        Ok(role)
+    }
+
+    pub async fn update(id: Uuid, role: &NewRole, pool: &PgPool) -> Result<Role, Error> {
+        let result = sqlx::query_as!(
+            Role,
+            "UPDATE roles SET name=$2, permissions=$3, position=$4 WHERE id=$1 RETURNING *",
+            id,
+            role.name,
+            role.permissions.bits(),
+            role.position
+        )
+        .fetch_one(pool)
+        .await;
+
+        match result {
+            Ok(role) => Ok(role),
+            Err(sqlx::Error::Database(error)) if error.is_unique_violation() => {
+                Err(Error::ROLE_ALREADY_EXISTS)
+            }
+            Err(error) => Err(error)?,
+        }
+    }
+
+    /// Deletes a role, moving its members back to the default role.
+    pub async fn delete(&self, pool: &PgPool) -> Result<(), Error> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query!(
+            "UPDATE members SET role_id = (SELECT id FROM roles WHERE group_id=$2 AND is_default)
+                WHERE role_id=$1",
+            self.id,
+            self.group_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!("DELETE FROM roles WHERE id=$1", self.id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+/// What a member is allowed to do within a group.
+#[derive(Debug, Clone, Copy)]
+pub struct Membership {
+    pub permissions: Permission,
+    pub position: i32,
+    pub is_owner: bool,
+}
+
+impl Membership {
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions.contains(permission)
+    }
+
+    /// Whether this member may manage members holding `role`, or `role` itself.
+    pub fn outranks(&self, role: &Role) -> bool {
+        self.is_owner || self.position > role.position
+    }
+
+    /// Whether this member may hand out every permission of `permissions`.
+    pub fn can_grant(&self, permissions: Permission) -> bool {
+        self.permissions.contains(permissions)
+    }
+}
+
+pub async fn fetch_membership(
+    user_id: Uuid,
+    group: &Group,
+    pool: &PgPool,
+) -> Result<Option<Membership>, Error> {
+    let role = sqlx::query!(
+        "SELECT roles.permissions, roles.position FROM members
+            JOIN roles ON roles.id = members.role_id
+        WHERE members.user_id = $1 AND members.group_id = $2",
+        user_id,
+        group.id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let membership = role.map(|role| match group.owner_id == user_id {
+        // Both sides of a direct message are equals, neither of them can manage it.
+        _ if group.is_direct => Membership {
+            permissions: Permission::SEND_MESSAGES,
+            position: 0,
+            is_owner: false,
+        },
+        true => Membership {
+            permissions: Permission::all(),
+            position: i32::MAX,
+            is_owner: true,
+        },
+        false => Membership {
+            permissions: Permission::from_bits_truncate(role.permissions),
+            position: role.position,
+            is_owner: false,
+        },
+    });
+
+    Ok(membership)
+}
+
+/// Checks that the user is a member of the group holding `permission`,
+/// which the owner of the group always does.
+pub async fn require_permission(
+    user_id: Uuid,
+    group: &Group,
+    permission: Permission,
+    pool: &PgPool,
+) -> Result<Membership, Error> {
+    let membership = fetch_membership(user_id, group, pool)
+        .await?
+        .ok_or(Error::INSUFFICIENT_PERMISSIONS)?;
+
+    if !membership.has(permission) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    Ok(membership)
+}
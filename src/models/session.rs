@@ -0,0 +1,151 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{prelude::FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+
+    #[serde(skip)]
+    pub refresh_token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewSession {
+    pub refresh_token_hash: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: NaiveDateTime,
+}
+
+impl Session {
+    pub async fn fetch(id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<Option<Session>, Error> {
+        let session = sqlx::query_as!(
+            Session,
+            "SELECT * FROM sessions WHERE id=$1 AND user_id=$2 AND expires_at > (now() AT TIME ZONE 'UTC')",
+            id,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Whether a session is unexpired and the tokens of its user haven't been
+    /// invalidated since `token_version`, for connections that outlive the
+    /// request they were authenticated with.
+    pub async fn is_active(
+        id: Uuid,
+        user_id: Uuid,
+        token_version: i32,
+        pool: &PgPool,
+    ) -> Result<bool, Error> {
+        let active = sqlx::query_scalar!(
+            r#"SELECT EXISTS(
+                SELECT 1 FROM sessions
+                JOIN users ON users.id = sessions.user_id
+                WHERE sessions.id=$1 AND sessions.user_id=$2 AND users.token_version=$3
+                    AND sessions.expires_at > (now() AT TIME ZONE 'UTC')
+            ) AS "active!""#,
+            id,
+            user_id,
+            token_version
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(active)
+    }
+
+    pub async fn fetch_all(user_id: Uuid, pool: &PgPool) -> Result<Vec<Session>, Error> {
+        let sessions = sqlx::query_as!(
+            Session,
+            "SELECT * FROM sessions WHERE user_id=$1 AND expires_at > (now() AT TIME ZONE 'UTC') ORDER BY last_seen_at DESC",
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    pub async fn create(
+        user_id: Uuid,
+        session: NewSession,
+        pool: &PgPool,
+    ) -> Result<Session, Error> {
+        let session = sqlx::query_as!(
+            Session,
+            "INSERT INTO sessions (user_id, refresh_token_hash, user_agent, ip_address, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            user_id,
+            session.refresh_token_hash,
+            session.user_agent,
+            session.ip_address,
+            session.expires_at
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Swaps the refresh token of an active session for a new one. Returns
+    /// `None` if the old token was already rotated, revoked or expired.
+    pub async fn rotate(
+        refresh_token_hash: &str,
+        session: NewSession,
+        pool: &PgPool,
+    ) -> Result<Option<Session>, Error> {
+        let session = sqlx::query_as!(
+            Session,
+            "UPDATE sessions SET refresh_token_hash=$2, user_agent=$3, ip_address=$4, expires_at=$5, last_seen_at=(now() AT TIME ZONE 'UTC') WHERE refresh_token_hash=$1 AND expires_at > (now() AT TIME ZONE 'UTC') RETURNING *",
+            refresh_token_hash,
+            session.refresh_token_hash,
+            session.user_agent,
+            session.ip_address,
+            session.expires_at
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    pub async fn delete(id: Uuid, user_id: Uuid, pool: &PgPool) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE id=$1 AND user_id=$2",
+            id,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_by_refresh_token(
+        refresh_token_hash: &str,
+        pool: &PgPool,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            "DELETE FROM sessions WHERE refresh_token_hash=$1",
+            refresh_token_hash
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
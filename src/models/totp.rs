@@ -0,0 +1,124 @@
+use chrono::NaiveDateTime;
+use sqlx::{prelude::FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::Error;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Totp {
+    pub user_id: Uuid,
+    /// Base32 encoded shared secret.
+    pub secret: String,
+    /// Whether enrollment was confirmed with a valid code.
+    pub enabled: bool,
+    /// The last time step a code was accepted for, so codes can't be reused.
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Totp {
+    pub async fn fetch(user_id: Uuid, pool: &PgPool) -> Result<Option<Totp>, Error> {
+        let totp = sqlx::query_as!(Totp, "SELECT * FROM totp WHERE user_id=$1", user_id)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(totp)
+    }
+
+    /// Starts enrollment with a new secret, replacing any unconfirmed one.
+    /// Returns `None` if two-factor authentication is already enabled.
+    pub async fn enroll(
+        user_id: Uuid,
+        secret: String,
+        pool: &PgPool,
+    ) -> Result<Option<Totp>, Error> {
+        let totp = sqlx::query_as!(
+            Totp,
+            "INSERT INTO totp (user_id, secret) VALUES ($1, $2)
+                ON CONFLICT (user_id) DO UPDATE SET secret=EXCLUDED.secret, last_used_step=NULL
+                WHERE totp.enabled = false
+                RETURNING *",
+            user_id,
+            secret
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(totp)
+    }
+
+    /// Confirms enrollment, replacing the recovery codes of the user.
+    pub async fn enable(
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+        pool: &PgPool,
+    ) -> Result<(), Error> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query!("UPDATE totp SET enabled=true WHERE user_id=$1", user_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM recovery_codes WHERE user_id=$1", user_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!(
+            "INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::text[])",
+            user_id,
+            recovery_code_hashes
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn disable(user_id: Uuid, pool: &PgPool) -> Result<(), Error> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query!("DELETE FROM totp WHERE user_id=$1", user_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query!("DELETE FROM recovery_codes WHERE user_id=$1", user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Marks `step` as used. Returns `false` if a code for this or a later
+    /// step was already accepted.
+    pub async fn use_step(user_id: Uuid, step: i64, pool: &PgPool) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "UPDATE totp SET last_used_step=$2
+                WHERE user_id=$1 AND (last_used_step IS NULL OR last_used_step < $2)",
+            user_id,
+            step
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Consumes a recovery code. Returns `false` if it doesn't exist or was
+    /// already used.
+    pub async fn use_recovery_code(
+        user_id: Uuid,
+        code_hash: &str,
+        pool: &PgPool,
+    ) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "DELETE FROM recovery_codes WHERE user_id=$1 AND code_hash=$2",
+            user_id,
+            code_hash
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,10 +1,12 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::{prelude::FromRow, PgPool};
+use sqlx::{prelude::FromRow, PgConnection, PgPool};
 use uuid::Uuid;
 
 use crate::Error;
 
+use super::Group;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
@@ -13,9 +15,23 @@ pub struct User {
     pub username: String,
     #[serde(skip)]
     pub password_hash: String,
+    /// Bumped to invalidate every access token issued to the user so far.
+    #[serde(skip)]
+    pub token_version: i32,
     pub created_at: NaiveDateTime,
 }
 
+/// Groups affected by deleting a user.
+#[derive(Debug, Default)]
+pub struct DeletedUser {
+    /// Direct messages, and groups no other member could inherit.
+    pub deleted_groups: Vec<Uuid>,
+    /// Groups the user owned, now owned by their longest standing member.
+    pub transferred_groups: Vec<Group>,
+    /// Remaining groups the user was a member of, transferred ones included.
+    pub left_groups: Vec<Uuid>,
+}
+
 impl User {
     pub async fn fetch(id: Uuid, pool: &PgPool) -> Result<Option<User>, Error> {
         let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id=$1", id)
@@ -58,4 +74,116 @@ impl User {
 
         Ok(user)
     }
+
+    /// Changes the password and invalidates every access token and session
+    /// of the user except `session_id`.
+    pub async fn update_password(
+        id: Uuid,
+        password_hash: String,
+        session_id: Uuid,
+        pool: &PgPool,
+    ) -> Result<User, Error> {
+        let mut transaction = pool.begin().await?;
+
+        invalidate_tokens(id, Some(session_id), &mut transaction).await?;
+        let user = sqlx::query_as!(
+            User,
+            "UPDATE users SET password_hash=$2 WHERE id=$1 RETURNING *",
+            id,
+            password_hash
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Deletes the user, handing each owned group over to its longest-standing
+    /// member. Returns the ids of the user's direct messages and of owned groups
+    /// that had no one left, which are deleted along with the user.
+    pub async fn delete(id: Uuid, pool: &PgPool) -> Result<DeletedUser, Error> {
+        let mut transaction = pool.begin().await?;
+
+        let mut deleted_groups = sqlx::query_scalar!(
+            "DELETE FROM groups WHERE id IN (
+                SELECT group_id FROM direct_messages WHERE first_user_id=$1 OR second_user_id=$1
+            ) RETURNING id",
+            id
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        let transferred_groups = sqlx::query_as!(
+            Group,
+            "UPDATE groups SET owner_id = heirs.user_id
+                FROM (
+                    SELECT DISTINCT ON (group_id) group_id, user_id FROM members
+                        WHERE user_id <> $1
+                        ORDER BY group_id, joined_at
+                ) heirs
+                WHERE groups.id = heirs.group_id AND groups.owner_id = $1
+                RETURNING groups.*",
+            id
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        deleted_groups.extend(
+            sqlx::query_scalar!("DELETE FROM groups WHERE owner_id=$1 RETURNING id", id)
+                .fetch_all(&mut *transaction)
+                .await?,
+        );
+
+        let left_groups = sqlx::query_scalar!("SELECT group_id FROM members WHERE user_id=$1", id)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        sqlx::query!("DELETE FROM users WHERE id=$1", id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(DeletedUser {
+            deleted_groups,
+            transferred_groups,
+            left_groups,
+        })
+    }
+
+    /// Invalidates every access token and session of the user.
+    pub async fn revoke_tokens(id: Uuid, pool: &PgPool) -> Result<(), Error> {
+        let mut transaction = pool.begin().await?;
+
+        invalidate_tokens(id, None, &mut transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+async fn invalidate_tokens(
+    id: Uuid,
+    except_session_id: Option<Uuid>,
+    connection: &mut PgConnection,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE users SET token_version = token_version + 1 WHERE id=$1",
+        id
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM sessions WHERE user_id=$1 AND id IS DISTINCT FROM $2",
+        id,
+        except_session_id
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
 }
@@ -130,7 +130,7 @@ where
 
         let extractor = self.extractor.clone();
         let context = self.context.clone();
-        let config = self.config.clone();
+        let config = self.config;
 
         Box::pin(async move {
             let (mut parts, body) = request.into_parts();
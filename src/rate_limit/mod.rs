@@ -39,7 +39,7 @@ impl Bucket {
         let refill = self.config.refill_rate * elapsed.as_secs();
         self.tokens += refill.clamp(0, self.config.capacity);
 
-        if self.tokens <= 0 {
+        if self.tokens == 0 {
             false
         } else {
             self.tokens -= 1;
@@ -66,7 +66,7 @@ impl Buckets {
     pub fn acquire(&self, key: Key, config: BucketConfiguration) -> bool {
         let mut bucket = self
             .buckets
-            .entry(key.clone())
+            .entry(key)
             .or_insert(Bucket::new(config));
 
         bucket.acquire()
@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, State},
+    http::header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+    response::IntoResponse,
+    routing::get,
+    Extension, Router,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::{group, Attachment, User},
+    rate_limit::RateLimitLayer,
+    Context, Error,
+};
+
+/// Downloads an attachment of a message in the group. Images are shown inline,
+/// anything else is served as a download.
+pub async fn get_attachment(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let attachment = Attachment::fetch(attachment_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ATTACHMENT)?;
+    let data = context
+        .storage()
+        .get(&attachment.key)
+        .await?
+        .ok_or(Error::UNKNOWN_ATTACHMENT)?;
+
+    let disposition = if attachment.content_type.starts_with("image/") {
+        "inline"
+    } else {
+        "attachment"
+    };
+    // Keep the header ASCII-only; the full name is part of the message anyway.
+    let filename = attachment
+        .filename
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' | ' ' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    Ok((
+        [
+            (CONTENT_TYPE, attachment.content_type),
+            (
+                CONTENT_DISPOSITION,
+                format!("{disposition}; filename=\"{filename}\""),
+            ),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (CACHE_CONTROL, "private, max-age=86400".to_string()),
+        ],
+        data,
+    ))
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    Router::new()
+        .route("/:attachment_id", get(get_attachment))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("attachments")
+                .with_capacity(50)
+                .with_refill_rate(5)
+                .build(context),
+        )
+}
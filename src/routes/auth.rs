@@ -5,41 +5,68 @@ use argon2::{
     Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
 };
 use axum::{
-    extract::{ConnectInfo, Request, State},
+    extract::{ConnectInfo, Path, Request, State},
     middleware::{from_fn_with_state, Next},
-    response::IntoResponse,
-    routing::{get, post},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
     Extension, Json, Router,
 };
-use axum_extra::extract::{cookie::Cookie, CookieJar};
+use axum_extra::{
+    extract::{cookie::Cookie, CookieJar},
+    headers::UserAgent,
+    TypedHeader,
+};
+use chrono::{TimeDelta, Utc};
 use garde::Validate;
 use jsonwebtoken::{
     decode, encode, errors::ErrorKind as JwtErrorKind, get_current_timestamp, Algorithm,
     DecodingKey, EncodingKey, Header, Validation,
 };
-use rand::rngs::OsRng;
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use time::{Duration, OffsetDateTime};
 use tokio::task::{self};
 use uuid::Uuid;
 
 use crate::{
-    common::{turnstile::VerifyTokenRequest, Garde, RouterExt},
-    models::User,
+    common::{totp, turnstile::VerifyTokenRequest, Garde, RouterExt},
+    models::{NewSession, Session, Totp, User},
     rate_limit::{Component, Key, RateLimitLayer},
     Context, Error,
 };
 
-const TOKEN_EXPIRATION: u64 = 24 * 60 * 60;
+const TOKEN_EXPIRATION: u64 = 15 * 60;
+const MFA_TOKEN_EXPIRATION: u64 = 5 * 60;
+const REFRESH_TOKEN_EXPIRATION: u64 = 30 * 24 * 60 * 60;
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+/// How often event streams check that the session they were opened with is still active.
+const SESSION_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+const TOKEN_COOKIE: &str = "token";
+const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Scope {
+    Access,
+    /// Password was verified, but a second factor still has to be provided.
+    MfaPending,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Claims {
     sub: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sid: Option<Uuid>,
+    ver: i32,
+    scope: Scope,
     exp: u64,
     iat: u64,
 }
 
-fn hash_password(password: String) -> Result<String, Error> {
+pub fn hash_password(password: String) -> Result<String, Error> {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
 
@@ -47,20 +74,23 @@ fn hash_password(password: String) -> Result<String, Error> {
     Ok(hash.to_string())
 }
 
-fn verify_password(password: &str, password_hash: &str) -> Result<bool, Error> {
+pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, Error> {
     let argon2 = Argon2::default();
-    let password_hash = PasswordHash::parse(&password_hash, Encoding::B64)?;
+    let password_hash = PasswordHash::parse(password_hash, Encoding::B64)?;
 
     Ok(argon2
         .verify_password(password.as_bytes(), &password_hash)
         .is_ok())
 }
 
-fn generate_token(user: &User, private_key: &[u8]) -> Result<String, Error> {
+fn generate_token(user: &User, session: &Session, private_key: &[u8]) -> Result<String, Error> {
     Ok(encode(
         &Header::new(Algorithm::ES256),
         &Claims {
             sub: user.id,
+            sid: Some(session.id),
+            ver: user.token_version,
+            scope: Scope::Access,
             exp: get_current_timestamp() + TOKEN_EXPIRATION,
             iat: get_current_timestamp(),
         },
@@ -68,6 +98,21 @@ fn generate_token(user: &User, private_key: &[u8]) -> Result<String, Error> {
     )?)
 }
 
+fn generate_mfa_token(user: &User, private_key: &[u8]) -> Result<String, Error> {
+    Ok(encode(
+        &Header::new(Algorithm::ES256),
+        &Claims {
+            sub: user.id,
+            sid: None,
+            ver: user.token_version,
+            scope: Scope::MfaPending,
+            exp: get_current_timestamp() + MFA_TOKEN_EXPIRATION,
+            iat: get_current_timestamp(),
+        },
+        &EncodingKey::from_ec_pem(private_key).expect("invalid encoding key"),
+    )?)
+}
+
 fn verify_token(token: &str, public_key: &[u8]) -> Result<Claims, Error> {
     let token_data = match decode::<Claims>(
         token,
@@ -78,7 +123,10 @@ fn verify_token(token: &str, public_key: &[u8]) -> Result<Claims, Error> {
         Err(err)
             if matches!(
                 err.kind(),
-                JwtErrorKind::ExpiredSignature | JwtErrorKind::InvalidSignature
+                JwtErrorKind::ExpiredSignature
+                    | JwtErrorKind::InvalidSignature
+                    | JwtErrorKind::InvalidToken
+                    | JwtErrorKind::Json(..)
             ) =>
         {
             return Err(Error::INVALID_TOKEN)
@@ -89,6 +137,110 @@ fn verify_token(token: &str, public_key: &[u8]) -> Result<Claims, Error> {
     Ok(token_data.claims)
 }
 
+fn generate_refresh_token() -> String {
+    OsRng
+        .sample_iter(&Alphanumeric)
+        .take(REFRESH_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+fn hash_refresh_token(refresh_token: &str) -> String {
+    format!("{:x}", Sha256::digest(refresh_token.as_bytes()))
+}
+
+/// Where a session was last used from, recorded on login and on every refresh.
+#[derive(Debug, Clone)]
+struct Device {
+    user_agent: Option<String>,
+    ip_address: String,
+}
+
+impl Device {
+    fn new(user_agent: Option<TypedHeader<UserAgent>>, addr: SocketAddr) -> Self {
+        Self {
+            user_agent: user_agent.map(|TypedHeader(user_agent)| user_agent.to_string()),
+            ip_address: addr.ip().to_string(),
+        }
+    }
+
+    /// Builds the session row for a freshly generated refresh token.
+    fn into_session(self, refresh_token: &str) -> NewSession {
+        NewSession {
+            refresh_token_hash: hash_refresh_token(refresh_token),
+            user_agent: self.user_agent,
+            ip_address: Some(self.ip_address),
+            expires_at: Utc::now().naive_utc()
+                + TimeDelta::seconds(REFRESH_TOKEN_EXPIRATION as i64),
+        }
+    }
+}
+
+fn cookie(name: &'static str, value: String, expiration: u64) -> Cookie<'static> {
+    let expiration = OffsetDateTime::now_utc() + Duration::seconds(expiration as i64);
+
+    Cookie::build((name, value))
+        .path("/")
+        .http_only(true)
+        .expires(expiration)
+        .build()
+}
+
+fn add_session_cookies(
+    jar: CookieJar,
+    user: &User,
+    session: &Session,
+    refresh_token: String,
+    private_key: &[u8],
+) -> Result<CookieJar, Error> {
+    let token = generate_token(user, session, private_key)?;
+
+    Ok(jar
+        .add(cookie(TOKEN_COOKIE, token, TOKEN_EXPIRATION))
+        .add(cookie(
+            REFRESH_TOKEN_COOKIE,
+            refresh_token,
+            REFRESH_TOKEN_EXPIRATION,
+        )))
+}
+
+/// Replaces the access token of the current session, e.g. after the token
+/// version of the user was bumped.
+pub fn reissue_token(
+    jar: CookieJar,
+    user: &User,
+    session: &Session,
+    private_key: &[u8],
+) -> Result<CookieJar, Error> {
+    let token = generate_token(user, session, private_key)?;
+
+    Ok(jar.add(cookie(TOKEN_COOKIE, token, TOKEN_EXPIRATION)))
+}
+
+async fn start_session(
+    context: &Context,
+    jar: CookieJar,
+    user: &User,
+    device: Device,
+) -> Result<CookieJar, Error> {
+    let refresh_token = generate_refresh_token();
+    let session = device.into_session(&refresh_token);
+    let session = Session::create(user.id, session, context.pool()).await?;
+
+    add_session_cookies(
+        jar,
+        user,
+        &session,
+        refresh_token,
+        context.keys().private_key(),
+    )
+}
+
+pub fn remove_session_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::build(TOKEN_COOKIE).path("/"))
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE).path("/"))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct RegisterBody {
     #[garde(pattern(r#"^[a-zA-Z_][a-zA-Z0-9_]*$"#), length(min = 4, max = 16))]
@@ -99,7 +251,7 @@ pub struct RegisterBody {
     token: String,
 }
 
-fn validate_password_strength(password: &str, _: &()) -> garde::Result {
+pub fn validate_password_strength(password: &str, _: &()) -> garde::Result {
     if !password.chars().any(|char| char.is_uppercase()) {
         return Err(garde::Error::new(
             "password must contain at least one uppercase letter",
@@ -151,9 +303,11 @@ pub struct LoginBody {
 
 pub async fn login(
     State(context): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
     jar: CookieJar,
     Garde(Json(body)): Garde<Json<LoginBody>>,
-) -> Result<(CookieJar, Json<User>), Error> {
+) -> Result<Response, Error> {
     context
         .turnstile()
         .verify(VerifyTokenRequest {
@@ -166,47 +320,289 @@ pub async fn login(
         .await?
         .ok_or(Error::INVALID_CREDENTIALS)?;
 
-    if !verify_password(&body.password, &user.password_hash)? {
+    let password = body.password;
+    let password_hash = user.password_hash.clone();
+    let is_valid = task::spawn_blocking(move || verify_password(&password, &password_hash))
+        .await
+        .expect("failed to join blocking verify task")?;
+
+    if !is_valid {
         return Err(Error::INVALID_CREDENTIALS);
     };
 
-    let token = generate_token(&user, context.keys().private_key())?;
+    let totp = Totp::fetch(user.id, context.pool()).await?;
+    if totp.is_some_and(|totp| totp.enabled) {
+        let token = generate_mfa_token(&user, context.keys().private_key())?;
+        let jar = jar.add(cookie(TOKEN_COOKIE, token, MFA_TOKEN_EXPIRATION));
 
-    let expiration = OffsetDateTime::now_utc();
-    let expiration = expiration + Duration::seconds(TOKEN_EXPIRATION as i64);
+        return Ok((jar, Error::MFA_REQUIRED).into_response());
+    }
 
-    let cookie = Cookie::build(("token", token))
-        .path("/")
-        .expires(expiration)
-        .build();
-    let jar = jar.add(cookie);
+    let jar = start_session(&context, jar, &user, Device::new(user_agent, addr)).await?;
+
+    Ok((jar, Json(user)).into_response())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct MfaCodeBody {
+    /// Either a TOTP code or a recovery code.
+    #[garde(length(min = 6, max = 16))]
+    code: String,
+}
+
+/// Accepts a TOTP code of the user, or consumes one of their recovery codes.
+async fn verify_mfa_code(user: &User, code: &str, context: &Context) -> Result<(), Error> {
+    let totp = Totp::fetch(user.id, context.pool())
+        .await?
+        .filter(|totp| totp.enabled)
+        .ok_or(Error::MFA_NOT_ENROLLED)?;
+
+    let accepted = match totp::verify(&totp.secret, &user.username, code)? {
+        Some(step) => Totp::use_step(user.id, step as i64, context.pool()).await?,
+        None => {
+            let code_hash = totp::hash_recovery_code(code);
+            Totp::use_recovery_code(user.id, &code_hash, context.pool()).await?
+        }
+    };
+
+    if !accepted {
+        return Err(Error::INVALID_MFA_CODE);
+    }
+
+    Ok(())
+}
+
+pub async fn login_totp(
+    State(context): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    jar: CookieJar,
+    Garde(Json(body)): Garde<Json<MfaCodeBody>>,
+) -> Result<(CookieJar, Json<User>), Error> {
+    let token = jar.get(TOKEN_COOKIE).ok_or(Error::INVALID_TOKEN)?;
+    let claims = verify_token(token.value(), context.keys().public_key())?;
+
+    if claims.scope != Scope::MfaPending {
+        return Err(Error::INVALID_TOKEN);
+    }
+
+    let user = User::fetch(claims.sub, context.pool())
+        .await?
+        .ok_or(Error::INVALID_TOKEN)?;
+
+    if claims.ver != user.token_version {
+        return Err(Error::INVALID_TOKEN);
+    }
+
+    verify_mfa_code(&user, &body.code, &context).await?;
+
+    let jar = start_session(&context, jar, &user, Device::new(user_agent, addr)).await?;
+
+    Ok((jar, Json(user)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollTotpResponse {
+    secret: String,
+    uri: String,
+}
+
+pub async fn enroll_totp(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+) -> Result<Json<EnrollTotpResponse>, Error> {
+    let secret = totp::generate_secret();
+    let uri = totp::otpauth_uri(&secret, &user.username)?;
+
+    Totp::enroll(user.id, secret.clone(), context.pool())
+        .await?
+        .ok_or(Error::MFA_ALREADY_ENABLED)?;
+
+    Ok(Json(EnrollTotpResponse { secret, uri }))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
+}
+
+pub async fn confirm_totp(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Garde(Json(body)): Garde<Json<MfaCodeBody>>,
+) -> Result<Json<RecoveryCodesResponse>, Error> {
+    let totp = Totp::fetch(user.id, context.pool())
+        .await?
+        .ok_or(Error::MFA_NOT_ENROLLED)?;
+
+    if totp.enabled {
+        return Err(Error::MFA_ALREADY_ENABLED);
+    }
+
+    let step =
+        totp::verify(&totp.secret, &user.username, &body.code)?.ok_or(Error::INVALID_MFA_CODE)?;
+    if !Totp::use_step(user.id, step as i64, context.pool()).await? {
+        return Err(Error::INVALID_MFA_CODE);
+    }
+
+    let recovery_codes = (0..totp::RECOVERY_CODE_COUNT)
+        .map(|_| totp::generate_recovery_code())
+        .collect::<Vec<_>>();
+    let recovery_code_hashes = recovery_codes
+        .iter()
+        .map(|code| totp::hash_recovery_code(code))
+        .collect::<Vec<_>>();
+
+    Totp::enable(user.id, &recovery_code_hashes, context.pool()).await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Garde(Json(body)): Garde<Json<MfaCodeBody>>,
+) -> Result<(), Error> {
+    verify_mfa_code(&user, &body.code, &context).await?;
+    Totp::disable(user.id, context.pool()).await?;
+
+    Ok(())
+}
+
+pub async fn refresh(
+    State(context): State<Context>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    jar: CookieJar,
+) -> Result<(CookieJar, Json<User>), Error> {
+    let old_refresh_token = jar.get(REFRESH_TOKEN_COOKIE).ok_or(Error::INVALID_TOKEN)?;
+    let old_refresh_token_hash = hash_refresh_token(old_refresh_token.value());
+
+    let refresh_token = generate_refresh_token();
+    let session = Device::new(user_agent, addr).into_session(&refresh_token);
+    let session = Session::rotate(&old_refresh_token_hash, session, context.pool())
+        .await?
+        .ok_or(Error::INVALID_TOKEN)?;
+
+    let user = User::fetch(session.user_id, context.pool())
+        .await?
+        .ok_or(Error::INVALID_TOKEN)?;
+
+    let jar = add_session_cookies(
+        jar,
+        &user,
+        &session,
+        refresh_token,
+        context.keys().private_key(),
+    )?;
 
     Ok((jar, Json(user)))
 }
 
-pub async fn logout(jar: CookieJar) -> CookieJar {
-    jar.remove("token")
+pub async fn logout(State(context): State<Context>, jar: CookieJar) -> Result<CookieJar, Error> {
+    if let Some(refresh_token) = jar.get(REFRESH_TOKEN_COOKIE) {
+        let refresh_token_hash = hash_refresh_token(refresh_token.value());
+        Session::delete_by_refresh_token(&refresh_token_hash, context.pool()).await?;
+    }
+
+    Ok(remove_session_cookies(jar))
+}
+
+pub async fn logout_all(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    jar: CookieJar,
+) -> Result<CookieJar, Error> {
+    User::revoke_tokens(user.id, context.pool()).await?;
+
+    Ok(remove_session_cookies(jar))
 }
 
 pub async fn me(Extension(user): Extension<User>) -> Json<User> {
     Json(user)
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    session: Session,
+    current: bool,
+}
+
+pub async fn get_sessions(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Extension(current): Extension<Session>,
+) -> Result<Json<Vec<SessionResponse>>, Error> {
+    let sessions = Session::fetch_all(user.id, context.pool()).await?;
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id == current.id,
+            session,
+        })
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_session(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(session_id): Path<Uuid>,
+) -> Result<(), Error> {
+    if !Session::delete(session_id, user.id, context.pool()).await? {
+        return Err(Error::UNKNOWN_SESSION);
+    }
+
+    Ok(())
+}
+
+/// Resolves once the session an event stream was opened with is revoked,
+/// expires, or has its tokens invalidated, so the stream can be ended.
+pub async fn session_revoked(context: Context, session: Session, user: User) {
+    loop {
+        tokio::time::sleep(SESSION_CHECK_INTERVAL).await;
+
+        match Session::is_active(session.id, user.id, user.token_version, context.pool()).await {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(error) => tracing::warn!("failed to check session {}: {error:?}", session.id),
+        }
+    }
+}
+
 pub async fn middleware(
     State(ctx): State<Context>,
     jar: CookieJar,
     mut request: Request,
     next: Next,
 ) -> Result<impl IntoResponse, Error> {
-    let token = jar.get("token").ok_or(Error::INVALID_TOKEN)?;
+    let token = jar.get(TOKEN_COOKIE).ok_or(Error::INVALID_TOKEN)?;
     let token = token.value();
 
     let claims = verify_token(token, ctx.keys().public_key())?;
+    if claims.scope != Scope::Access {
+        return Err(Error::MFA_REQUIRED);
+    }
+
+    let session_id = claims.sid.ok_or(Error::INVALID_TOKEN)?;
+    let session = Session::fetch(session_id, claims.sub, ctx.pool())
+        .await?
+        .ok_or(Error::INVALID_TOKEN)?;
     let user = User::fetch(claims.sub, ctx.pool())
         .await?
         .ok_or(Error::INVALID_TOKEN)?;
 
+    if claims.ver != user.token_version {
+        return Err(Error::INVALID_TOKEN);
+    }
+
     request.extensions_mut().insert(user);
+    request.extensions_mut().insert(session);
 
     Ok(next.run(request).await)
 }
@@ -215,12 +611,39 @@ pub fn create_router(context: Context) -> Router<Context> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/login/totp", post(login_totp))
+        .route("/refresh", post(refresh))
         .route("/logout", post(logout))
+        .route_with_layer(
+            "/logout-all",
+            post(logout_all),
+            from_fn_with_state(context.clone(), middleware),
+        )
+        .route_with_layer(
+            "/totp",
+            post(enroll_totp).delete(disable_totp),
+            from_fn_with_state(context.clone(), middleware),
+        )
+        .route_with_layer(
+            "/totp/confirm",
+            post(confirm_totp),
+            from_fn_with_state(context.clone(), middleware),
+        )
         .route_with_layer(
             "/me",
             get(me),
             from_fn_with_state(context.clone(), middleware),
         )
+        .route_with_layer(
+            "/sessions",
+            get(get_sessions),
+            from_fn_with_state(context.clone(), middleware),
+        )
+        .route_with_layer(
+            "/sessions/:session_id",
+            delete(revoke_session),
+            from_fn_with_state(context.clone(), middleware),
+        )
         .layer(
             RateLimitLayer::builder()
                 .with_fn(|ConnectInfo(addr): ConnectInfo<SocketAddr>| {
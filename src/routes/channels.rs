@@ -0,0 +1,237 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Path, State},
+    response::{sse::Event as SseEvent, Sse},
+    routing::{get, patch, post},
+    Extension, Json, Router,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio_stream::Stream;
+use uuid::Uuid;
+
+use crate::{
+    common::{sse_to_subscription, Garde, LastEventId, Subscription},
+    event::{DeleteChannelEvent, Event},
+    models::{
+        group,
+        role::{self, Permission},
+        Channel, Group, NewChannel, Session, User,
+    },
+    rate_limit::RateLimitLayer,
+    Context, Error,
+};
+
+use super::{auth, messages};
+
+/// Path of routes that act on a channel, where leaving out the channel means
+/// the default channel of the group.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ChannelParams {
+    pub group_id: Uuid,
+    pub channel_id: Option<Uuid>,
+}
+
+/// Fetches the channel a route acts on, checking that the user is a member of its group.
+pub async fn fetch_with_membership_check(
+    user_id: Uuid,
+    params: ChannelParams,
+    pool: &PgPool,
+) -> Result<(Group, Channel), Error> {
+    let group = group::fetch_with_membership_check(user_id, params.group_id, pool).await?;
+
+    let channel = match params.channel_id {
+        Some(channel_id) => Channel::fetch(channel_id, group.id, pool)
+            .await?
+            .ok_or(Error::UNKNOWN_CHANNEL)?,
+        None => Channel::fetch_default(group.id, pool).await?,
+    };
+
+    Ok((group, channel))
+}
+
+fn require_owner(user: &User, group: &Group) -> Result<(), Error> {
+    if group.is_direct || group.owner_id != user.id {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    Ok(())
+}
+
+pub async fn get_channels(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<Vec<Channel>>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let channels = Channel::fetch_all(group.id, context.pool()).await?;
+
+    Ok(Json(channels))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChannelBody {
+    #[garde(length(min = 1, max = 32), pattern(r"^[a-z0-9_-]+$"))]
+    name: String,
+    #[garde(length(max = 256))]
+    topic: Option<String>,
+    #[garde(range(min = 0))]
+    position: i32,
+}
+
+pub async fn create_channel(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+    Garde(Json(body)): Garde<Json<CreateChannelBody>>,
+) -> Result<Json<Channel>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    require_owner(&user, &group)?;
+
+    let channel = Channel::create(
+        group.id,
+        &NewChannel {
+            name: body.name,
+            topic: body.topic.filter(|topic| !topic.trim().is_empty()),
+            position: body.position,
+        },
+        context.pool(),
+    )
+    .await?;
+
+    context.subscriptions().send(
+        &Event::CreateChannel(channel.clone()),
+        &Subscription::Group(group.id),
+    );
+
+    Ok(Json(channel))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateChannelBody {
+    #[garde(length(min = 1, max = 32), pattern(r"^[a-z0-9_-]+$"))]
+    name: Option<String>,
+    /// An empty topic removes it.
+    #[garde(length(max = 256))]
+    topic: Option<String>,
+    #[garde(range(min = 0))]
+    position: Option<i32>,
+}
+
+pub async fn update_channel(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, channel_id)): Path<(Uuid, Uuid)>,
+    Garde(Json(body)): Garde<Json<UpdateChannelBody>>,
+) -> Result<Json<Channel>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    require_owner(&user, &group)?;
+
+    let channel = Channel::fetch(channel_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_CHANNEL)?;
+
+    let topic = match body.topic {
+        Some(topic) if topic.trim().is_empty() => None,
+        Some(topic) => Some(topic),
+        None => channel.topic,
+    };
+
+    let channel = Channel::update(
+        channel.id,
+        &NewChannel {
+            name: body.name.unwrap_or(channel.name),
+            topic,
+            position: body.position.unwrap_or(channel.position),
+        },
+        context.pool(),
+    )
+    .await?;
+
+    context
+        .subscriptions()
+        .send_to_channel(&Event::UpdateChannel(channel.clone()), &channel);
+
+    Ok(Json(channel))
+}
+
+pub async fn delete_channel(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, channel_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    require_owner(&user, &group)?;
+
+    let channel = Channel::fetch(channel_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_CHANNEL)?;
+
+    channel.delete(context.pool()).await?;
+
+    context.subscriptions().send_to_channel(
+        &Event::DeleteChannel(DeleteChannelEvent {
+            group_id: group.id,
+            channel_id: channel.id,
+        }),
+        &channel,
+    );
+
+    Ok(())
+}
+
+pub async fn updates(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Extension(session): Extension<Session>,
+    Path(params): Path<ChannelParams>,
+    last_event_id: LastEventId,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, Error> {
+    let (_, channel) = fetch_with_membership_check(user.id, params, context.pool()).await?;
+
+    Ok(sse_to_subscription(
+        context.subscriptions(),
+        &Subscription::Channel(channel.id),
+        user.id,
+        last_event_id,
+        auth::session_revoked(context.clone(), session, user),
+    ))
+}
+
+pub async fn start_typing(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(params): Path<ChannelParams>,
+) -> Result<(), Error> {
+    let (group, channel) = fetch_with_membership_check(user.id, params, context.pool()).await?;
+    role::require_permission(user.id, &group, Permission::SEND_MESSAGES, context.pool()).await?;
+
+    context
+        .indicators()
+        .start_typing(&user, &channel, context.subscriptions());
+
+    Ok(())
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    let channel_routes = Router::new()
+        .route("/", get(get_channels).post(create_channel))
+        .route("/:channel_id", patch(update_channel).delete(delete_channel))
+        .route("/:channel_id/updates", get(updates))
+        .route("/:channel_id/typing", post(start_typing))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("channels")
+                .with_capacity(10)
+                .with_refill_rate(1)
+                .build(context.clone()),
+        );
+
+    Router::new()
+        .merge(channel_routes)
+        .nest("/:channel_id/messages", messages::create_router(context))
+}
@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Path, State},
+    http::header::{CACHE_CONTROL, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+
+use crate::{common::storage, Context, Error};
+
+/// Serves group icons, which are public. Their keys contain a hash of the
+/// image, so they never change.
+pub async fn get_icon(
+    State(context): State<Context>,
+    Path(key): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let key = format!("icons/{key}");
+    if key
+        .split('/')
+        .any(|segment| segment.is_empty() || segment == "..")
+    {
+        return Err(Error::UNKNOWN_FILE);
+    }
+
+    let data = context
+        .storage()
+        .get(&key)
+        .await?
+        .ok_or(Error::UNKNOWN_FILE)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, storage::content_type(&key)),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            (CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        data,
+    ))
+}
+
+pub fn create_router() -> Router<Context> {
+    Router::new().route("/icons/*key", get(get_icon))
+}
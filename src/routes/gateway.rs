@@ -0,0 +1,374 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message as WsMessage, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    middleware::from_fn_with_state,
+    response::Response,
+    routing::get,
+    Extension, Router,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    select,
+    time::{interval, Instant},
+};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+use crate::{
+    common::{Envelope, EventStream},
+    event::Event,
+    models::{
+        role::{self, Permission},
+        Group, Session, User,
+    },
+    rate_limit::{BucketConfiguration, Component, Key, RateLimitLayer},
+    Context, Error,
+};
+
+use super::{
+    auth,
+    channels::{self, ChannelParams},
+    messages::{self, CreateMessageBody},
+};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+/// Dispatches a client can fall behind on acknowledging before the connection
+/// is closed with a policy violation, see [`AckOp`].
+const MAX_UNACKED: u64 = 512;
+
+const OPS_BUCKET: BucketConfiguration = BucketConfiguration {
+    capacity: 25,
+    refill_rate: 1,
+};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayParams {
+    last_event_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartTypingOp {
+    group_id: Uuid,
+    channel_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessageOp {
+    group_id: Uuid,
+    channel_id: Option<Uuid>,
+    #[serde(flatten)]
+    body: CreateMessageBody,
+}
+
+/// Acknowledges every dispatch up to and including `seq`.
+///
+/// Clients must ack as they go: once more than [`MAX_UNACKED`] dispatches are
+/// unacknowledged the connection is closed with code 1008. Reconnecting with
+/// the `id` of the last handled dispatch as `lastEventId` resumes where it left off.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AckOp {
+    seq: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", content = "data")]
+#[serde(rename_all = "camelCase")]
+pub enum Op {
+    StartTyping(StartTypingOp),
+    SendMessage(SendMessageOp),
+    Ack(AckOp),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyFrame {
+    user: User,
+    groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DispatchFrame {
+    /// Position of the dispatch on this connection, acknowledged with [`AckOp`].
+    seq: u64,
+    /// Id of the event, to resume from on the next connection.
+    id: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "op", content = "data")]
+#[serde(rename_all = "camelCase")]
+pub enum Frame {
+    Ready(ReadyFrame),
+    Dispatch(DispatchFrame),
+    Error(Error),
+}
+
+enum Action {
+    Receive(Option<WsMessage>),
+    Dispatch(Box<Envelope>),
+    Heartbeat,
+}
+
+struct Connection {
+    socket: WebSocket,
+    context: Context,
+    user: User,
+    /// Session the connection was opened with, which ends it when revoked.
+    session: Session,
+
+    events: EventStream,
+    seq: u64,
+    acked: u64,
+    last_seen: Instant,
+}
+
+impl Connection {
+    fn new(
+        socket: WebSocket,
+        context: Context,
+        user: User,
+        session: Session,
+        events: EventStream,
+    ) -> Self {
+        Self {
+            socket,
+            context,
+            user,
+            session,
+
+            events,
+            seq: 0,
+            acked: 0,
+            last_seen: Instant::now(),
+        }
+    }
+
+    async fn run(mut self, groups: Vec<Group>) {
+        let ready = Frame::Ready(ReadyFrame {
+            user: self.user.clone(),
+            groups,
+        });
+
+        if self.send(&ready).await.is_err() {
+            return;
+        }
+
+        let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+
+        loop {
+            let action = select! {
+                message = self.socket.recv() => Action::Receive(message.and_then(Result::ok)),
+                Some(event) = self.events.next() => Action::Dispatch(Box::new(event)),
+                _ = heartbeat.tick() => Action::Heartbeat,
+            };
+
+            let result = match action {
+                Action::Receive(Some(message)) => self.receive(message).await,
+                Action::Receive(None) => break,
+                Action::Dispatch(event) => self.dispatch(*event).await,
+                Action::Heartbeat => self.heartbeat().await,
+            };
+
+            if result.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn send(&mut self, frame: &Frame) -> Result<(), axum::Error> {
+        let frame = serde_json::to_string(frame).expect("failed to serialize frame");
+
+        self.socket.send(WsMessage::Text(frame)).await
+    }
+
+    async fn close(&mut self, code: u16, reason: &'static str) -> Result<(), axum::Error> {
+        let frame = CloseFrame {
+            code,
+            reason: reason.into(),
+        };
+
+        self.socket.send(WsMessage::Close(Some(frame))).await?;
+        Err(axum::Error::new(reason))
+    }
+
+    async fn dispatch(&mut self, envelope: Envelope) -> Result<(), axum::Error> {
+        self.seq += 1;
+
+        if self.seq - self.acked > MAX_UNACKED {
+            return self
+                .close(close_code::POLICY, "too many unacknowledged events")
+                .await;
+        }
+
+        let frame = Frame::Dispatch(DispatchFrame {
+            seq: self.seq,
+            id: envelope.id,
+            event: envelope.event,
+        });
+
+        self.send(&frame).await
+    }
+
+    async fn heartbeat(&mut self) -> Result<(), axum::Error> {
+        if self.last_seen.elapsed() > HEARTBEAT_TIMEOUT {
+            return self.close(close_code::AWAY, "heartbeat timed out").await;
+        }
+
+        let active = Session::is_active(
+            self.session.id,
+            self.user.id,
+            self.user.token_version,
+            self.context.pool(),
+        )
+        .await;
+
+        match active {
+            Ok(true) => {}
+            Ok(false) => return self.close(close_code::POLICY, "session revoked").await,
+            Err(error) => tracing::warn!("failed to check session {}: {error:?}", self.session.id),
+        }
+
+        self.socket.send(WsMessage::Ping(Vec::new())).await
+    }
+
+    async fn receive(&mut self, message: WsMessage) -> Result<(), axum::Error> {
+        self.last_seen = Instant::now();
+
+        let op = match message {
+            WsMessage::Text(text) => {
+                serde_json::from_str::<Op>(&text).map_err(|_| Error::INVALID_OP)
+            }
+            WsMessage::Binary(..) => Err(Error::INVALID_OP),
+            WsMessage::Close(..) => return Err(axum::Error::new("connection closed")),
+            WsMessage::Ping(..) | WsMessage::Pong(..) => return Ok(()),
+        };
+
+        let result = match op {
+            Ok(op) => self.handle(op).await,
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(error) => self.send(&Frame::Error(error)).await,
+        }
+    }
+
+    async fn handle(&mut self, op: Op) -> Result<(), Error> {
+        let key = Key("gateway", Component::Uuid(self.user.id));
+        if !self.context.buckets().acquire(key, OPS_BUCKET) {
+            return Err(Error::RATE_LIMITED);
+        }
+
+        match op {
+            Op::StartTyping(StartTypingOp {
+                group_id,
+                channel_id,
+            }) => {
+                let (group, channel) = channels::fetch_with_membership_check(
+                    self.user.id,
+                    ChannelParams {
+                        group_id,
+                        channel_id,
+                    },
+                    self.context.pool(),
+                )
+                .await?;
+                role::require_permission(
+                    self.user.id,
+                    &group,
+                    Permission::SEND_MESSAGES,
+                    self.context.pool(),
+                )
+                .await?;
+
+                self.context.indicators().start_typing(
+                    &self.user,
+                    &channel,
+                    self.context.subscriptions(),
+                );
+            }
+            Op::SendMessage(SendMessageOp {
+                group_id,
+                channel_id,
+                body,
+            }) => {
+                body.validate()?;
+
+                let (group, channel) = channels::fetch_with_membership_check(
+                    self.user.id,
+                    ChannelParams {
+                        group_id,
+                        channel_id,
+                    },
+                    self.context.pool(),
+                )
+                .await?;
+
+                messages::send_message(
+                    &self.context,
+                    &self.user,
+                    &group,
+                    &channel,
+                    body,
+                    Vec::new(),
+                )
+                .await?;
+            }
+            Op::Ack(AckOp { seq }) => {
+                if seq > self.seq {
+                    return Err(Error::INVALID_OP);
+                }
+
+                self.acked = self.acked.max(seq);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn gateway(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Extension(session): Extension<Session>,
+    Query(params): Query<GatewayParams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, Error> {
+    let groups = Group::fetch_all(user.id, context.pool()).await?;
+    let events = context
+        .subscriptions()
+        .subscribe_user(user.id, &groups, params.last_event_id);
+
+    Ok(upgrade.on_upgrade(move |socket| {
+        Connection::new(socket, context, user, session, events).run(groups)
+    }))
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    let auth_middleware = from_fn_with_state(context.clone(), auth::middleware);
+
+    Router::new()
+        .route("/", get(gateway))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("gateway_connect")
+                .with_capacity(5)
+                .with_refill_rate(1)
+                .build(context),
+        )
+        .layer(auth_middleware)
+}
@@ -1,6 +1,14 @@
-use super::{auth, invites, messages};
+use super::{attachments, auth, channels, invites, members, messages, roles};
 use crate::{
-    common::{sse_to_subscription, Garde, Subscription}, models::{group, Group, NewGroup, User}, rate_limit::RateLimitLayer, Context, Error
+    common::{sse_to_subscription, storage, Garde, LastEventId, Subscription},
+    event::{self, DeleteGroupEvent},
+    models::{
+        group,
+        role::{self, Permission},
+        Channel, Group, GroupWithReadStates, NewGroup, ReadState, Session, UpdatedGroup, User,
+    },
+    rate_limit::RateLimitLayer,
+    Context, Error,
 };
 use axum::{
     extract::{Path, State},
@@ -9,8 +17,10 @@ use axum::{
     routing::{get, post},
     Extension, Json, Router,
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
 use garde::Validate;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::convert::Infallible;
 use tokio_stream::Stream;
 use uuid::Uuid;
@@ -18,8 +28,23 @@ use uuid::Uuid;
 pub async fn get_groups(
     State(context): State<Context>,
     Extension(user): Extension<User>,
-) -> Result<Json<Vec<Group>>, Error> {
-    let groups = Group::fetch_all(user.id, context.pool()).await?;
+) -> Result<Json<Vec<GroupWithReadStates>>, Error> {
+    let mut groups = Group::fetch_all(user.id, context.pool()).await?;
+    groups.retain(|group| !group.is_direct);
+
+    let read_states = ReadState::fetch_all(user.id, context.pool()).await?;
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            let group_states = read_states
+                .iter()
+                .filter(|state| state.group_id == group.id)
+                .cloned()
+                .collect();
+
+            GroupWithReadStates::new(group, group_states)
+        })
+        .collect();
 
     Ok(Json(groups))
 }
@@ -45,6 +70,11 @@ pub async fn create_group(
     )
     .await?;
 
+    context.subscriptions().send(
+        &event::Event::JoinGroup(group.clone()),
+        &Subscription::User(user.id),
+    );
+
     Ok(Json(group))
 }
 
@@ -58,64 +88,185 @@ pub async fn get_group(
     Ok(Json(group))
 }
 
+const MAX_ICON_SIZE: usize = 1024 * 1024;
+const MAX_ICON_ENCODED_LENGTH: usize = MAX_ICON_SIZE.div_ceil(3) * 4;
+const MAX_EDIT_WINDOW: i32 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateGroupBody {
+    #[garde(length(min = 4, max = 16), pattern(r"^[a-zA-Z0-9_]+$"))]
+    pub name: Option<String>,
+    /// An empty description removes it.
+    #[garde(length(max = 256))]
+    pub description: Option<String>,
+    /// Base64-encoded PNG, JPEG, GIF or WebP image; an empty string removes the icon.
+    #[garde(length(max = MAX_ICON_ENCODED_LENGTH))]
+    pub icon: Option<String>,
+    /// Seconds after sending during which messages can be edited; 0 removes the limit.
+    #[garde(range(min = 0, max = MAX_EDIT_WINDOW))]
+    pub edit_window: Option<i32>,
+}
+
+/// Saves a base64-encoded icon and returns its storage key.
+async fn store_icon(context: &Context, group_id: Uuid, encoded: &str) -> Result<String, Error> {
+    let data = STANDARD.decode(encoded).map_err(|_| Error::INVALID_IMAGE)?;
+    let extension = storage::image_extension(&data).ok_or(Error::INVALID_IMAGE)?;
+
+    let key = format!("icons/{group_id}/{:x}.{extension}", Sha256::digest(&data));
+    context
+        .storage()
+        .put(&key, data.into(), storage::content_type(&key))
+        .await?;
+
+    Ok(key)
+}
+
+pub async fn update_group(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+    Garde(Json(body)): Garde<Json<UpdateGroupBody>>,
+) -> Result<Json<Group>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    role::require_permission(user.id, &group, Permission::MANAGE_GROUP, context.pool()).await?;
+
+    let icon = match body.icon.as_deref() {
+        Some("") => None,
+        Some(encoded) => Some(store_icon(&context, group.id, encoded).await?),
+        None => group.icon.clone(),
+    };
+    let description = match body.description {
+        Some(description) if description.trim().is_empty() => None,
+        Some(description) => Some(description),
+        None => group.description.clone(),
+    };
+    let edit_window = match body.edit_window {
+        Some(0) => None,
+        Some(edit_window) => Some(edit_window),
+        None => group.edit_window,
+    };
+
+    let updated = Group::update(
+        group.id,
+        &UpdatedGroup {
+            name: body.name.unwrap_or(group.name),
+            description,
+            icon,
+            edit_window,
+        },
+        context.pool(),
+    )
+    .await?;
+
+    if let Some(old_icon) = group
+        .icon
+        .filter(|icon| updated.icon.as_ref() != Some(icon))
+    {
+        if let Err(error) = context.storage().delete(&old_icon).await {
+            tracing::warn!("failed to delete icon {old_icon}: {error}");
+        }
+    }
+
+    context.subscriptions().send(
+        &event::Event::UpdateGroup(updated.clone()),
+        &Subscription::Group(updated.id),
+    );
+
+    Ok(Json(updated))
+}
+
 pub async fn delete_group(
     State(context): State<Context>,
     Extension(user): Extension<User>,
     Path(group_id): Path<Uuid>,
 ) -> Result<(), Error> {
     let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    role::require_permission(user.id, &group, Permission::DELETE_GROUP, context.pool()).await?;
+
+    let channels = Channel::fetch_all(group.id, context.pool()).await?;
     Group::delete(group.id, context.pool()).await?;
 
+    if let Some(icon) = &group.icon {
+        if let Err(error) = context.storage().delete(icon).await {
+            tracing::warn!("failed to delete icon {icon}: {error}");
+        }
+    }
+
+    context.subscriptions().broadcast(
+        &event::Event::DeleteGroup(DeleteGroupEvent { group_id: group.id }),
+        group.id,
+        &channels,
+    );
+
     Ok(())
 }
 
-pub async fn get_members(
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferGroupBody {
+    #[garde(skip)]
+    pub user_id: Uuid,
+}
+
+pub async fn transfer_group(
     State(context): State<Context>,
     Extension(user): Extension<User>,
     Path(group_id): Path<Uuid>,
-) -> Result<Json<Vec<User>>, Error> {
+    Garde(Json(body)): Garde<Json<TransferGroupBody>>,
+) -> Result<Json<Group>, Error> {
     let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-    let members = Group::fetch_members(group.id, context.pool()).await?;
 
-    Ok(Json(members))
+    if group.is_direct {
+        return Err(Error::DIRECT_MESSAGE_GROUP);
+    }
+    if group.owner_id != user.id {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+    if body.user_id == user.id {
+        return Ok(Json(group));
+    }
+
+    let group = group.transfer(body.user_id, context.pool()).await?;
+
+    context.subscriptions().send(
+        &event::Event::UpdateGroup(group.clone()),
+        &Subscription::Group(group.id),
+    );
+
+    Ok(Json(group))
 }
 
 pub async fn updates(
     State(context): State<Context>,
     Extension(user): Extension<User>,
+    Extension(session): Extension<Session>,
     Path(group_id): Path<Uuid>,
+    last_event_id: LastEventId,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
     let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
 
     Ok(sse_to_subscription(
         context.subscriptions(),
         &Subscription::Group(group.id),
+        user.id,
+        last_event_id,
+        auth::session_revoked(context.clone(), session, user),
     ))
 }
 
-pub async fn start_typing(
-    State(context): State<Context>,
-    Extension(user): Extension<User>,
-    Path(group_id): Path<Uuid>,
-) -> Result<(), Error> {
-    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-
-    context
-        .indicators()
-        .start_typing(&user, &group, context.subscriptions());
-
-    Ok(())
-}
-
 pub fn create_router(context: Context) -> Router<Context> {
     let auth_middleware = from_fn_with_state(context.clone(), auth::middleware);
 
     let groups_routes = Router::new()
         .route("/", get(get_groups).post(create_group))
-        .route("/:group_id", get(get_group).delete(delete_group))
+        .route(
+            "/:group_id",
+            get(get_group).patch(update_group).delete(delete_group),
+        )
         .route("/:group_id/updates", get(updates))
-        .route("/:group_id/members", get(get_members))
-        .route("/:group_id/typing", post(start_typing))
+        .route("/:group_id/typing", post(channels::start_typing))
+        .route("/:group_id/transfer", post(transfer_group))
         .layer(
             RateLimitLayer::builder()
                 .with_user("groups")
@@ -130,6 +281,23 @@ pub fn create_router(context: Context) -> Router<Context> {
             "/:group_id/messages",
             messages::create_router(context.clone()),
         )
+        .nest(
+            "/:group_id/channels",
+            channels::create_router(context.clone()),
+        )
+        .nest(
+            "/:group_id/attachments",
+            attachments::create_router(context.clone()),
+        )
+        .nest(
+            "/:group_id/members",
+            members::create_router(context.clone()),
+        )
+        .nest(
+            "/:group_id/bans",
+            members::create_bans_router(context.clone()),
+        )
+        .nest("/:group_id/roles", roles::create_router(context.clone()))
         .nest("/:group_id/invites", invites::create_router(context))
         .layer(auth_middleware)
 }
@@ -1,15 +1,24 @@
 use axum::{
+    body::Bytes,
     extract::{Path, State},
     middleware::from_fn_with_state,
-    routing::{get, post},
+    routing::{delete, get},
     Extension, Json, Router,
 };
+use chrono::{TimeDelta, Utc};
 use garde::Validate;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
-    models::{group::fetch_with_membership_check, invite::Invite, Group, User},
+    common::{Garde, Subscription},
+    event::{AddMemberEvent, Event},
+    models::{
+        group::fetch_with_membership_check,
+        invite::{Invite, InvitePreview, NewInvite},
+        role::{self, Permission},
+        Group, GroupMember, User,
+    },
     rate_limit::RateLimitLayer,
     Context, Error,
 };
@@ -22,40 +31,114 @@ pub async fn get_invites(
     Path(group_id): Path<Uuid>,
 ) -> Result<Json<Vec<Invite>>, Error> {
     let group = fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-    if user.id != group.owner_id {
-        return Err(Error::INSUFFICIENT_PERMISSIONS);
-    }
-    
+    role::require_permission(user.id, &group, Permission::MANAGE_INVITES, context.pool()).await?;
+
     let invites = Invite::fetch_all(group.id, context.pool()).await?;
 
     Ok(Json(invites))
 }
 
+/// Invites can be made to last at most 30 days.
+const MAX_INVITE_AGE: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInviteBody {
+    /// Vanity code, which only the owner can set.
+    #[garde(length(min = 3, max = 32), pattern(r"^[a-zA-Z0-9_-]+$"))]
+    code: Option<String>,
+    #[garde(range(min = 1, max = 1000))]
+    max_uses: Option<i32>,
+    /// Seconds until the invite expires.
+    #[garde(range(min = 60, max = MAX_INVITE_AGE))]
+    max_age: Option<i64>,
+}
+
 pub async fn create_invite(
     State(context): State<Context>,
     Path(group_id): Path<Uuid>,
     Extension(user): Extension<User>,
+    body: Bytes,
 ) -> Result<Json<Invite>, Error> {
+    // The body can be left out to create an invite with the defaults.
+    let body = if body.is_empty() {
+        CreateInviteBody::default()
+    } else {
+        let Json(body) =
+            Json::<CreateInviteBody>::from_bytes(&body).map_err(|_| Error::VALIDATION)?;
+        body
+    };
+    body.validate()?;
+
     let group = fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-    if user.id != group.owner_id {
+    role::require_permission(user.id, &group, Permission::CREATE_INVITES, context.pool()).await?;
+
+    if body.code.is_some() && group.owner_id != user.id {
         return Err(Error::INSUFFICIENT_PERMISSIONS);
     }
 
-    let invite = Invite::create(group.id, user.id, context.pool()).await?;
+    let expires_at = body
+        .max_age
+        .map(|max_age| Utc::now().naive_utc() + TimeDelta::seconds(max_age));
+
+    let invite = Invite::create(
+        group.id,
+        user.id,
+        &NewInvite {
+            code: body.code,
+            max_uses: body.max_uses,
+            expires_at,
+        },
+        context.invite_codes(),
+        context.pool(),
+    )
+    .await?;
 
     Ok(Json(invite))
 }
 
+/// Creators can always revoke their own invites, anyone else needs `MANAGE_INVITES`.
+pub async fn delete_invite(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, invite_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), Error> {
+    let group = fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let invite = Invite::fetch(invite_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_INVITE)?;
+
+    if invite.user_id != user.id {
+        role::require_permission(user.id, &group, Permission::MANAGE_INVITES, context.pool())
+            .await?;
+    }
+
+    invite.delete(context.pool()).await?;
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, Deserialize, Validate)]
 pub struct InviteParams {
-    #[garde(length(min = 8, max = 8), pattern(r"^[a-zA-Z0-9]{8}$"))]
+    #[garde(length(min = 3, max = 32), pattern(r"^[a-zA-Z0-9_-]+$"))]
     code: String,
 }
 
+pub async fn get_invite_preview(
+    State(context): State<Context>,
+    Garde(Path(InviteParams { code })): Garde<Path<InviteParams>>,
+) -> Result<Json<InvitePreview>, Error> {
+    let preview = Invite::fetch_preview(&code, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_INVITE)?;
+
+    Ok(Json(preview))
+}
+
 pub async fn accept_invite(
     State(context): State<Context>,
     Extension(user): Extension<User>,
-    Path(InviteParams { code }): Path<InviteParams>,
+    Garde(Path(InviteParams { code })): Garde<Path<InviteParams>>,
 ) -> Result<(), Error> {
     let invite = Invite::fetch_by_code(&code, context.pool())
         .await?
@@ -65,7 +148,27 @@ pub async fn accept_invite(
         return Err(Error::ALREADY_MEMBER);
     }
 
-    invite.accept(user.id, context.pool()).await?;
+    let group = Group::fetch(invite.group_id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_GROUP)?;
+
+    let member = invite.accept(user.id, context.pool()).await?;
+    let group_id = group.id;
+
+    context
+        .subscriptions()
+        .send(&Event::JoinGroup(group), &Subscription::User(user.id));
+    context.subscriptions().send(
+        &Event::AddMember(AddMemberEvent {
+            group_id,
+            member: GroupMember {
+                user,
+                role_id: member.role_id,
+                joined_at: member.joined_at,
+            },
+        }),
+        &Subscription::Group(group_id),
+    );
 
     Ok(())
 }
@@ -73,6 +176,7 @@ pub async fn accept_invite(
 pub fn create_router(context: Context) -> Router<Context> {
     Router::new()
         .route("/", get(get_invites).post(create_invite))
+        .route("/:invite_id", delete(delete_invite))
         .layer(
             RateLimitLayer::builder()
                 .with_user("invites")
@@ -86,7 +190,7 @@ pub fn create_code_router(context: Context) -> Router<Context> {
     let auth_middleware = from_fn_with_state(context.clone(), auth::middleware);
 
     Router::new()
-        .route("/:code", post(accept_invite))
+        .route("/:code", get(get_invite_preview).post(accept_invite))
         .layer(
             RateLimitLayer::builder()
                 .with_user("invites")
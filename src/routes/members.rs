@@ -0,0 +1,251 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get, patch, post},
+    Extension, Json, Router,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    common::{Garde, Subscription},
+    event::{Event, LeaveGroupEvent, RemovalReason, RemoveMemberEvent},
+    models::{
+        group::{self, Group},
+        role::{self, Membership, Permission},
+        Ban, Channel, GroupMember, Member, Role, User,
+    },
+    rate_limit::RateLimitLayer,
+    Context, Error,
+};
+
+pub async fn get_members(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<Vec<GroupMember>>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let members = Group::fetch_members(group.id, context.pool()).await?;
+
+    Ok(Json(members))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMemberBody {
+    #[garde(skip)]
+    role_id: Uuid,
+}
+
+pub async fn update_member(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, member_id)): Path<(Uuid, Uuid)>,
+    Garde(Json(body)): Garde<Json<UpdateMemberBody>>,
+) -> Result<Json<Member>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let membership =
+        role::require_permission(user.id, &group, Permission::MANAGE_ROLES, context.pool()).await?;
+
+    let member = Member::fetch(member_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_MEMBER)?;
+    let current_role = Role::fetch(member.role_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ROLE)?;
+    let new_role = Role::fetch(body.role_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ROLE)?;
+
+    let is_owner = member.user_id == group.owner_id;
+    if (is_owner && !membership.is_owner)
+        || !membership.outranks(&current_role)
+        || !membership.outranks(&new_role)
+    {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    let member = member.set_role(new_role.id, context.pool()).await?;
+
+    Ok(Json(member))
+}
+
+/// Fails unless `membership` may act on the member: the owner is out of
+/// everyone's reach, and anyone else only of those ranked above their role.
+async fn check_outranks(
+    membership: &Membership,
+    group: &Group,
+    member: &Member,
+    context: &Context,
+) -> Result<(), Error> {
+    if member.user_id == group.owner_id {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    let role = Role::fetch(member.role_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ROLE)?;
+    if !membership.outranks(&role) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    Ok(())
+}
+
+/// Tells the group a member is gone and the removed user to drop the group.
+pub async fn notify_removal(
+    context: &Context,
+    group_id: Uuid,
+    user_id: Uuid,
+    reason: RemovalReason,
+) -> Result<(), Error> {
+    // Channel streams need to see the removal too, so they can be closed.
+    let channels = Channel::fetch_all(group_id, context.pool()).await?;
+
+    context.subscriptions().broadcast(
+        &Event::RemoveMember(RemoveMemberEvent {
+            group_id,
+            user_id,
+            reason,
+        }),
+        group_id,
+        &channels,
+    );
+    context.subscriptions().send(
+        &Event::LeaveGroup(LeaveGroupEvent { group_id }),
+        &Subscription::User(user_id),
+    );
+
+    Ok(())
+}
+
+/// Leaves the group when targeting yourself, kicks the member otherwise.
+pub async fn remove_member(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, member_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+
+    if group.is_direct {
+        return Err(Error::DIRECT_MESSAGE_GROUP);
+    }
+
+    if member_id == user.id {
+        if group.owner_id == user.id {
+            return Err(Error::OWNER_CANNOT_LEAVE);
+        }
+
+        Member::delete(user.id, group.id, context.pool()).await?;
+        notify_removal(&context, group.id, user.id, RemovalReason::Leave).await?;
+
+        return Ok(());
+    }
+
+    let membership =
+        role::require_permission(user.id, &group, Permission::KICK_MEMBERS, context.pool()).await?;
+    let member = Member::fetch(member_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_MEMBER)?;
+    check_outranks(&membership, &group, &member, &context).await?;
+
+    if Member::delete(member.user_id, group.id, context.pool()).await? {
+        notify_removal(&context, group.id, member.user_id, RemovalReason::Kick).await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct BanMemberBody {
+    #[garde(length(max = 512))]
+    reason: Option<String>,
+}
+
+pub async fn ban_member(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, member_id)): Path<(Uuid, Uuid)>,
+    Garde(Json(body)): Garde<Json<BanMemberBody>>,
+) -> Result<Json<Ban>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let membership =
+        role::require_permission(user.id, &group, Permission::BAN_MEMBERS, context.pool()).await?;
+
+    if member_id == user.id {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    match Member::fetch(member_id, group.id, context.pool()).await? {
+        Some(member) => check_outranks(&membership, &group, &member, &context).await?,
+        None => {
+            User::fetch(member_id, context.pool())
+                .await?
+                .ok_or(Error::UNKNOWN_USER)?;
+        }
+    }
+
+    let (ban, removed) =
+        Ban::create(group.id, member_id, user.id, body.reason, context.pool()).await?;
+    if removed {
+        notify_removal(&context, group.id, member_id, RemovalReason::Ban).await?;
+    }
+
+    Ok(Json(ban))
+}
+
+pub async fn get_bans(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<Vec<Ban>>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    role::require_permission(user.id, &group, Permission::BAN_MEMBERS, context.pool()).await?;
+
+    let bans = Ban::fetch_all(group.id, context.pool()).await?;
+
+    Ok(Json(bans))
+}
+
+pub async fn delete_ban(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, banned_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    role::require_permission(user.id, &group, Permission::BAN_MEMBERS, context.pool()).await?;
+
+    if !Ban::delete(group.id, banned_id, context.pool()).await? {
+        return Err(Error::UNKNOWN_BAN);
+    }
+
+    Ok(())
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    Router::new()
+        .route("/", get(get_members))
+        .route("/:user_id", patch(update_member).delete(remove_member))
+        .route("/:user_id/ban", post(ban_member))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("members")
+                .with_capacity(10)
+                .with_refill_rate(1)
+                .build(context),
+        )
+}
+
+pub fn create_bans_router(context: Context) -> Router<Context> {
+    Router::new()
+        .route("/", get(get_bans))
+        .route("/:user_id", delete(delete_ban))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("members")
+                .with_capacity(10)
+                .with_refill_rate(1)
+                .build(context),
+        )
+}
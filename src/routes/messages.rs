@@ -1,25 +1,45 @@
+use std::collections::HashSet;
+
 use axum::{
-    extract::{Path, Query, State},
-    routing::{get, patch},
+    async_trait,
+    body::Bytes,
+    extract::{
+        multipart::Field, DefaultBodyLimit, FromRequest, Multipart, Path, Query, Request, State,
+    },
+    handler::Handler,
+    http::header::CONTENT_TYPE,
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    routing::{get, patch, post, put},
     Extension, Json, Router,
 };
+use chrono::{DateTime, Utc};
 use garde::Validate;
 use serde::{Deserialize, Serialize};
 use unicode_width::UnicodeWidthChar;
 use uuid::Uuid;
 
 use crate::{
-    common::{Garde, Subscription},
-    event::{DeleteMessageEvent, Event},
+    common::{storage, Garde, Subscription},
+    event::{DeleteMessageEvent, Event, MentionEvent, ReactionEvent},
     models::{
-        group::{self},
-        message::{self, can_modify_message, Message, MessageQuery, NewMessage},
-        User,
+        group::{self, Group},
+        message::{
+            self, can_modify_message, is_within_edit_window, Message, MessageCursor, MessageQuery,
+            MessageSearch, NewMessage, SearchResult, UpdatedMessage,
+        },
+        role::{self, Membership, Permission},
+        Attachment, Channel, Mention, MessageRevision, NewAttachment, Reaction, ReadState, User,
     },
     rate_limit::middleware::RateLimitLayer,
     Context, Error,
 };
 
+use super::{
+    auth,
+    channels::{self, ChannelParams},
+};
+
 fn sanitize(content: &str) -> String {
     content
         .chars()
@@ -48,39 +68,378 @@ fn sanitize_and_validate_message(content: &str, _: &()) -> garde::Result {
     Ok(())
 }
 
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Finds the `@username` mentions in sanitized content, along with whether
+/// `@everyone` was mentioned.
+fn parse_mentions(content: &str) -> (Vec<String>, bool) {
+    let mut usernames: Vec<String> = Vec::new();
+    let mut everyone = false;
+    let mut previous = None;
+
+    for (index, c) in content.char_indices() {
+        if c == '@' && !previous.is_some_and(is_username_char) {
+            let rest = &content[index + 1..];
+            let name = &rest[..rest.find(|c| !is_username_char(c)).unwrap_or(rest.len())];
+
+            match name {
+                "everyone" => everyone = true,
+                name if (4..=16).contains(&name.len()) && !usernames.iter().any(|u| u == name) => {
+                    usernames.push(name.to_string())
+                }
+                _ => {}
+            }
+        }
+
+        previous = Some(c);
+    }
+
+    (usernames, everyone)
+}
+
+/// Resolves the mentions in sanitized content to the members of the group
+/// they refer to. Mentions of anyone else are left as plain text, and so is
+/// `@everyone` unless the author has [`Permission::MENTION_EVERYONE`].
+async fn resolve_mentions(
+    content: &str,
+    group: &Group,
+    membership: &Membership,
+    context: &Context,
+) -> Result<(Vec<Uuid>, bool), Error> {
+    let (usernames, everyone) = parse_mentions(content);
+    let users = Mention::resolve(&usernames, group.id, context.pool()).await?;
+
+    Ok((
+        users.into_iter().map(|user| user.id).collect(),
+        everyone && membership.has(Permission::MENTION_EVERYONE),
+    ))
+}
+
+/// Sends a mention notification to the user stream of everyone a message
+/// mentions, except its author and those already notified about the previous
+/// version of an edited message.
+async fn notify_mentions(
+    context: &Context,
+    message: &Message,
+    previous: Option<&Message>,
+) -> Result<(), Error> {
+    let notified_everyone = previous.is_some_and(|previous| previous.mentions_everyone);
+    let everyone = message.mentions_everyone && !notified_everyone;
+
+    let recipients: Vec<Uuid> = if everyone {
+        Group::fetch_members(message.group_id, context.pool())
+            .await?
+            .into_iter()
+            .map(|member| member.user.id)
+            .collect()
+    } else if notified_everyone {
+        Vec::new()
+    } else {
+        message.mentions.iter().map(|user| user.id).collect()
+    };
+
+    let notified: HashSet<Uuid> = previous
+        .map(|previous| previous.mentions.iter().map(|user| user.id).collect())
+        .unwrap_or_default();
+
+    let event = Event::Mention(MentionEvent {
+        group_id: message.group_id,
+        channel_id: message.channel_id,
+        message_id: message.id,
+        author_id: message.user_id,
+        everyone,
+    });
+
+    for user_id in recipients {
+        if user_id != message.user_id && !notified.contains(&user_id) {
+            context
+                .subscriptions()
+                .send(&event, &Subscription::User(user_id));
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
 pub struct CreateMessageBody {
     #[garde(custom(sanitize_and_validate_message))]
     content: String,
+    #[garde(skip)]
+    reply_to: Option<Uuid>,
+    /// Any message of the thread, or the message to start a thread from.
+    #[garde(skip)]
+    thread_root: Option<Uuid>,
+}
+
+/// Body of a new message, sent either as JSON or as a multipart form with the
+/// JSON in a `payload_json` part and one `files` part per attachment.
+pub struct MessagePayload {
+    body: CreateMessageBody,
+    attachments: Vec<NewAttachment>,
+}
+
+impl MessagePayload {
+    const MAX_PAYLOAD_SIZE: usize = 64 * 1024;
+    /// Request body limit, enough for a full set of attachments.
+    const BODY_LIMIT: usize = Attachment::MAX_SIZE * Attachment::MAX_PER_MESSAGE + 64 * 1024;
+
+    async fn from_multipart(mut multipart: Multipart) -> Result<Self, Error> {
+        let mut body = None;
+        let mut attachments = Vec::new();
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| Error::INVALID_MULTIPART)?
+        {
+            match field.name() {
+                Some("payload_json") => {
+                    let data =
+                        read_field(field, Self::MAX_PAYLOAD_SIZE, Error::INVALID_MULTIPART).await?;
+                    let payload = serde_json::from_slice::<CreateMessageBody>(&data)
+                        .map_err(|_| Error::INVALID_MULTIPART)?;
+                    payload.validate()?;
+
+                    body = Some(payload);
+                }
+                Some("files") => {
+                    if attachments.len() == Attachment::MAX_PER_MESSAGE {
+                        return Err(Error::TOO_MANY_ATTACHMENTS);
+                    }
+
+                    let filename = sanitize_filename(field.file_name().unwrap_or_default());
+                    let content_type = field
+                        .content_type()
+                        .filter(|content_type| {
+                            Attachment::ALLOWED_CONTENT_TYPES.contains(content_type)
+                        })
+                        .ok_or(Error::UNSUPPORTED_ATTACHMENT)?
+                        .to_string();
+                    let data = read_field(field, Attachment::MAX_SIZE, Error::ATTACHMENT_TOO_LARGE)
+                        .await?;
+
+                    // Images are shown inline, so make sure they are what they claim to be.
+                    if content_type.starts_with("image/")
+                        && storage::image_extension(&data).is_none()
+                    {
+                        return Err(Error::UNSUPPORTED_ATTACHMENT);
+                    }
+
+                    attachments.push(NewAttachment {
+                        id: Uuid::new_v4(),
+                        filename,
+                        content_type,
+                        data,
+                    });
+                }
+                _ => return Err(Error::INVALID_MULTIPART),
+            }
+        }
+
+        Ok(Self {
+            body: body.ok_or(Error::INVALID_MULTIPART)?,
+            attachments,
+        })
+    }
+}
+
+/// Reads a multipart field, giving up with `error` as soon as it grows past `limit` bytes.
+async fn read_field(mut field: Field<'_>, limit: usize, error: Error) -> Result<Bytes, Error> {
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.chunk().await.map_err(|_| Error::INVALID_MULTIPART)? {
+        if data.len() + chunk.len() > limit {
+            return Err(error);
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(data.into())
+}
+
+#[async_trait]
+impl FromRequest<Context> for MessagePayload {
+    type Rejection = Response;
+
+    async fn from_request(request: Request, context: &Context) -> Result<Self, Self::Rejection> {
+        let is_multipart = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+        if !is_multipart {
+            let Garde(Json(body)) =
+                Garde::<Json<CreateMessageBody>>::from_request(request, context)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+
+            return Ok(Self {
+                body,
+                attachments: Vec::new(),
+            });
+        }
+
+        let multipart = Multipart::from_request(request, context)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        Self::from_multipart(multipart)
+            .await
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+/// Keeps the last path segment of an uploaded file's name, without control characters.
+fn sanitize_filename(filename: &str) -> String {
+    let filename = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(128)
+        .collect::<String>();
+
+    match filename.trim() {
+        "" => "file".to_string(),
+        filename => filename.to_string(),
+    }
+}
+
+/// Path of routes that act on a single message. Routes nested under a channel
+/// only find messages of that channel, the others find them in any channel.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct MessageParams {
+    group_id: Uuid,
+    channel_id: Option<Uuid>,
+    id: Uuid,
 }
 
 pub async fn create_message(
     State(context): State<Context>,
     Extension(user): Extension<User>,
-    Path(group_id): Path<Uuid>,
-    Garde(Json(body)): Garde<Json<CreateMessageBody>>,
+    Path(params): Path<ChannelParams>,
+    payload: MessagePayload,
 ) -> Result<Json<Message>, Error> {
-    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-
-    let message = Message::create(
-        &NewMessage {
-            user_id: user.id,
-            group_id: group.id,
-            content: sanitize(&body.content),
-        },
-        context.pool(),
+    let (group, channel) =
+        channels::fetch_with_membership_check(user.id, params, context.pool()).await?;
+    let message = send_message(
+        &context,
+        &user,
+        &group,
+        &channel,
+        payload.body,
+        payload.attachments,
     )
     .await?;
 
-    context.subscriptions().send(
-        &Event::NewMessage(message.clone()),
-        &Subscription::Group(group.id),
-    );
-    context.indicators().end_typing(&user, &group);
-
     Ok(Json(message))
 }
 
+pub async fn send_message(
+    context: &Context,
+    user: &User,
+    group: &Group,
+    channel: &Channel,
+    body: CreateMessageBody,
+    attachments: Vec<NewAttachment>,
+) -> Result<Message, Error> {
+    let membership =
+        role::require_permission(user.id, group, Permission::SEND_MESSAGES, context.pool()).await?;
+
+    if let Some(reply_to) = body.reply_to {
+        message::fetch_with_group_check(reply_to, group, context.pool()).await?;
+    }
+
+    let thread_root = match body.thread_root {
+        Some(id) => {
+            let parent = message::fetch_with_group_check(id, group, context.pool()).await?;
+
+            if parent.channel_id != channel.id {
+                return Err(Error::INVALID_THREAD);
+            }
+
+            Some(parent.thread_root.unwrap_or(parent.id))
+        }
+        None => None,
+    };
+
+    for attachment in &attachments {
+        if let Err(error) = context
+            .storage()
+            .put(
+                &attachment.key(),
+                attachment.data.clone(),
+                &attachment.content_type,
+            )
+            .await
+        {
+            delete_files(context, &attachments).await;
+            return Err(error.into());
+        }
+    }
+
+    let content = sanitize(&body.content);
+    let (mentions, mentions_everyone) =
+        match resolve_mentions(&content, group, &membership, context).await {
+            Ok(mentions) => mentions,
+            Err(error) => {
+                delete_files(context, &attachments).await;
+                return Err(error);
+            }
+        };
+
+    let new_message = NewMessage {
+        user_id: user.id,
+        group_id: group.id,
+        channel_id: channel.id,
+        content,
+        reply_to: body.reply_to,
+        thread_root,
+        attachments,
+        mentions,
+        mentions_everyone,
+    };
+
+    let message = match Message::create(&new_message, context.pool()).await {
+        Ok(message) => message,
+        Err(error) => {
+            delete_files(context, &new_message.attachments).await;
+            return Err(error);
+        }
+    };
+
+    context
+        .subscriptions()
+        .send_to_channel(&Event::NewMessage(message.clone()), channel);
+    context.indicators().end_typing(user, channel);
+
+    if let Err(error) = notify_mentions(context, &message, None).await {
+        tracing::warn!(
+            "failed to notify mentions of message {}: {error:?}",
+            message.id
+        );
+    }
+
+    Ok(message)
+}
+
+/// Removes the files of attachments that didn't make it into a message.
+async fn delete_files(context: &Context, attachments: &[NewAttachment]) {
+    for attachment in attachments {
+        let key = attachment.key();
+        if let Err(error) = context.storage().delete(&key).await {
+            tracing::warn!("failed to delete attachment {key}: {error}");
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct GetMessagesParams {
     #[garde(range(min = 10, max = 50))]
@@ -88,22 +447,46 @@ pub struct GetMessagesParams {
 
     #[garde(skip)]
     before: Option<Uuid>,
+    #[garde(skip)]
+    after: Option<Uuid>,
+    #[garde(skip)]
+    around: Option<Uuid>,
+    #[garde(skip)]
+    since: Option<DateTime<Utc>>,
+}
+
+impl GetMessagesParams {
+    fn cursor(&self) -> Result<MessageCursor, Error> {
+        let cursors = [
+            self.before.map(MessageCursor::Before),
+            self.after.map(MessageCursor::After),
+            self.around.map(MessageCursor::Around),
+            self.since
+                .map(|since| MessageCursor::Since(since.naive_utc())),
+        ];
+
+        let mut cursors = cursors.into_iter().flatten();
+        match (cursors.next(), cursors.next()) {
+            (None, _) => Ok(MessageCursor::Latest),
+            (Some(cursor), None) => Ok(cursor),
+            (Some(_), Some(_)) => Err(Error::CONFLICTING_CURSORS),
+        }
+    }
 }
 
 pub async fn get_messages(
     State(context): State<Context>,
-    Path(group_id): Path<Uuid>,
+    Path(params): Path<ChannelParams>,
     Extension(user): Extension<User>,
     Garde(Query(body)): Garde<Query<GetMessagesParams>>,
 ) -> Result<Json<Vec<Message>>, Error> {
-    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let (_, channel) =
+        channels::fetch_with_membership_check(user.id, params, context.pool()).await?;
 
     let messages = Message::fetch_all(
-        &MessageQuery {
-            limit: body.limit,
-            before: body.before,
-            group_id: group.id,
-        },
+        &MessageQuery::new(channel.id)
+            .cursor(body.cursor()?)
+            .limit(body.limit),
         context.pool(),
     )
     .await?;
@@ -117,59 +500,343 @@ pub struct EditMessageBody {
     content: String,
 }
 
+/// Fetches the message a route acts on along with its channel, checking that
+/// the user is a member of its group.
+async fn fetch_message(
+    user: &User,
+    params: MessageParams,
+    context: &Context,
+) -> Result<(Group, Channel, Message), Error> {
+    let group =
+        group::fetch_with_membership_check(user.id, params.group_id, context.pool()).await?;
+    let message = message::fetch_with_group_check(params.id, &group, context.pool()).await?;
+    if params
+        .channel_id
+        .is_some_and(|channel_id| channel_id != message.channel_id)
+    {
+        return Err(Error::UNKNOWN_MESSAGE);
+    }
+
+    let channel = Channel::fetch(message.channel_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_CHANNEL)?;
+
+    Ok((group, channel, message))
+}
+
 pub async fn edit_message(
-    Path((group_id, message_id)): Path<(Uuid, Uuid)>,
+    Path(params): Path<MessageParams>,
     Extension(user): Extension<User>,
     State(context): State<Context>,
     Garde(Json(body)): Garde<Json<EditMessageBody>>,
 ) -> Result<Json<Message>, Error> {
-    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-    let message = message::fetch_with_group_check(message_id, &group, context.pool()).await?;
+    let (group, channel, message) = fetch_message(&user, params, &context).await?;
 
     if !can_modify_message(&user, &message) {
         return Err(Error::INSUFFICIENT_PERMISSIONS);
     }
+    if !is_within_edit_window(&group, &message) {
+        return Err(Error::EDIT_WINDOW_EXPIRED);
+    }
+
+    let membership = role::fetch_membership(user.id, &group, context.pool())
+        .await?
+        .ok_or(Error::INSUFFICIENT_PERMISSIONS)?;
 
     let content = sanitize(&body.content);
-    let new_message = Message::edit(message.id, &content, context.pool()).await?;
+    let (mentions, mentions_everyone) =
+        resolve_mentions(&content, &group, &membership, &context).await?;
 
-    context.subscriptions().send(
-        &Event::EditMessage(new_message.clone()),
-        &Subscription::Group(group.id),
-    );
+    let new_message = Message::edit(
+        message.id,
+        &UpdatedMessage {
+            content,
+            mentions,
+            mentions_everyone,
+        },
+        context.pool(),
+    )
+    .await?;
+
+    context
+        .subscriptions()
+        .send_to_channel(&Event::EditMessage(new_message.clone()), &channel);
+
+    if let Err(error) = notify_mentions(&context, &new_message, Some(&message)).await {
+        tracing::warn!(
+            "failed to notify mentions of message {}: {error:?}",
+            new_message.id
+        );
+    }
 
     Ok(Json(new_message))
 }
 
 pub async fn delete_message(
-    Path((group_id, message_id)): Path<(Uuid, Uuid)>,
+    Path(params): Path<MessageParams>,
     Extension(user): Extension<User>,
     State(context): State<Context>,
 ) -> Result<(), Error> {
-    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
-    let message = message::fetch_with_group_check(message_id, &group, context.pool()).await?;
+    let (group, channel, message) = fetch_message(&user, params, &context).await?;
 
     if !can_modify_message(&user, &message) {
-        return Err(Error::INSUFFICIENT_PERMISSIONS);
+        role::require_permission(user.id, &group, Permission::MANAGE_MESSAGES, context.pool())
+            .await?;
     }
 
     Message::delete(message.id, context.pool()).await?;
 
-    context.subscriptions().send(
+    context.subscriptions().send_to_channel(
         &Event::DeleteMessage(DeleteMessageEvent {
-            group_id,
-            message_id,
+            group_id: group.id,
+            channel_id: channel.id,
+            message_id: message.id,
         }),
-        &Subscription::Group(group.id),
+        &channel,
     );
 
     Ok(())
 }
 
+fn validate_emoji(emoji: &str, _: &()) -> garde::Result {
+    match emojis::get(emoji) {
+        Some(_) => Ok(()),
+        None => Err(garde::Error::new("not an emoji")),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ReactionParams {
+    #[garde(skip)]
+    group_id: Uuid,
+    #[garde(skip)]
+    channel_id: Option<Uuid>,
+    #[garde(skip)]
+    id: Uuid,
+    #[garde(custom(validate_emoji))]
+    emoji: String,
+}
+
+impl ReactionParams {
+    /// Fully qualified form of the emoji, so that `❤` and `❤️` count as one.
+    fn emoji(&self) -> &str {
+        emojis::get(&self.emoji).map_or(&self.emoji, |emoji| emoji.as_str())
+    }
+}
+
+pub async fn add_reaction(
+    Garde(Path(params)): Garde<Path<ReactionParams>>,
+    Extension(user): Extension<User>,
+    State(context): State<Context>,
+) -> Result<(), Error> {
+    let message_params = MessageParams {
+        group_id: params.group_id,
+        channel_id: params.channel_id,
+        id: params.id,
+    };
+    let (group, channel, message) = fetch_message(&user, message_params, &context).await?;
+    role::require_permission(user.id, &group, Permission::SEND_MESSAGES, context.pool()).await?;
+
+    if Reaction::create(message.id, user.id, params.emoji(), context.pool()).await? {
+        context.subscriptions().send_to_channel(
+            &Event::ReactionAdd(ReactionEvent {
+                group_id: group.id,
+                channel_id: channel.id,
+                message_id: message.id,
+                user_id: user.id,
+                emoji: params.emoji().to_string(),
+            }),
+            &channel,
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn remove_reaction(
+    Garde(Path(params)): Garde<Path<ReactionParams>>,
+    Extension(user): Extension<User>,
+    State(context): State<Context>,
+) -> Result<(), Error> {
+    let message_params = MessageParams {
+        group_id: params.group_id,
+        channel_id: params.channel_id,
+        id: params.id,
+    };
+    let (group, channel, message) = fetch_message(&user, message_params, &context).await?;
+
+    if Reaction::delete(message.id, user.id, params.emoji(), context.pool()).await? {
+        context.subscriptions().send_to_channel(
+            &Event::ReactionRemove(ReactionEvent {
+                group_id: group.id,
+                channel_id: channel.id,
+                message_id: message.id,
+                user_id: user.id,
+                emoji: params.emoji().to_string(),
+            }),
+            &channel,
+        );
+    }
+
+    Ok(())
+}
+
+/// Lists the previous versions of a message, which only its author and those
+/// allowed to manage messages can see.
+pub async fn get_history(
+    Path(params): Path<MessageParams>,
+    Extension(user): Extension<User>,
+    State(context): State<Context>,
+) -> Result<Json<Vec<MessageRevision>>, Error> {
+    let (group, _, message) = fetch_message(&user, params, &context).await?;
+
+    if !can_modify_message(&user, &message) {
+        role::require_permission(user.id, &group, Permission::MANAGE_MESSAGES, context.pool())
+            .await?;
+    }
+
+    let revisions = MessageRevision::fetch_all(message.id, context.pool()).await?;
+
+    Ok(Json(revisions))
+}
+
+/// Marks the channel of a message as read up to it, letting the user's other
+/// sessions know.
+pub async fn ack_message(
+    Path(params): Path<MessageParams>,
+    Extension(user): Extension<User>,
+    State(context): State<Context>,
+) -> Result<Json<ReadState>, Error> {
+    let (_, channel, message) = fetch_message(&user, params, &context).await?;
+
+    let updated = ReadState::ack(user.id, &message, context.pool()).await?;
+    let read_state = ReadState::fetch(user.id, channel.id, context.pool()).await?;
+
+    if updated {
+        context.subscriptions().send(
+            &Event::ReadStateUpdate(read_state.clone()),
+            &Subscription::User(user.id),
+        );
+    }
+
+    Ok(Json(read_state))
+}
+
+/// Lists the messages of the thread a message starts or belongs to.
+pub async fn get_thread(
+    State(context): State<Context>,
+    Path(params): Path<MessageParams>,
+    Extension(user): Extension<User>,
+    Garde(Query(body)): Garde<Query<GetMessagesParams>>,
+) -> Result<Json<Vec<Message>>, Error> {
+    let (_, channel, message) = fetch_message(&user, params, &context).await?;
+
+    let messages = Message::fetch_all(
+        &MessageQuery::new(channel.id)
+            .thread(message.thread_root.unwrap_or(message.id))
+            .cursor(body.cursor()?)
+            .limit(body.limit),
+        context.pool(),
+    )
+    .await?;
+
+    Ok(Json(messages))
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMessagesParams {
+    #[garde(length(min = 1, max = 256))]
+    q: String,
+    #[garde(skip)]
+    author_id: Option<Uuid>,
+    #[garde(skip)]
+    after: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    before: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    has_link: Option<bool>,
+    #[garde(range(min = 1, max = 50))]
+    limit: Option<i64>,
+    #[garde(range(min = 0, max = 1000))]
+    offset: Option<i64>,
+}
+
+impl SearchMessagesParams {
+    fn into_search(self, group_ids: Vec<Uuid>, channel_id: Option<Uuid>) -> MessageSearch {
+        MessageSearch {
+            query: self.q,
+            group_ids,
+            channel_id,
+            author_id: self.author_id,
+            after: self.after.map(|after| after.naive_utc()),
+            before: self.before.map(|before| before.naive_utc()),
+            has_link: self.has_link,
+            limit: self.limit.unwrap_or(25),
+            offset: self.offset.unwrap_or(0),
+        }
+    }
+}
+
+/// Searches the messages of a group, or of one of its channels when the
+/// route is nested under it.
+pub async fn search_messages(
+    State(context): State<Context>,
+    Path(params): Path<ChannelParams>,
+    Extension(user): Extension<User>,
+    Garde(Query(query)): Garde<Query<SearchMessagesParams>>,
+) -> Result<Json<Vec<SearchResult>>, Error> {
+    let group =
+        group::fetch_with_membership_check(user.id, params.group_id, context.pool()).await?;
+
+    if let Some(channel_id) = params.channel_id {
+        Channel::fetch(channel_id, group.id, context.pool())
+            .await?
+            .ok_or(Error::UNKNOWN_CHANNEL)?;
+    }
+
+    let results = Message::search(
+        &query.into_search(vec![group.id], params.channel_id),
+        context.pool(),
+    )
+    .await?;
+
+    Ok(Json(results))
+}
+
+/// Searches the messages of every group the user is a member of.
+pub async fn search_all_messages(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Garde(Query(query)): Garde<Query<SearchMessagesParams>>,
+) -> Result<Json<Vec<SearchResult>>, Error> {
+    let group_ids = Group::fetch_all(user.id, context.pool())
+        .await?
+        .into_iter()
+        .map(|group| group.id)
+        .collect();
+
+    let results = Message::search(&query.into_search(group_ids, None), context.pool()).await?;
+
+    Ok(Json(results))
+}
+
 pub fn create_router(context: Context) -> Router<Context> {
     Router::new()
-        .route("/", get(get_messages).post(create_message))
+        .route(
+            "/",
+            get(get_messages)
+                .post(create_message.layer(DefaultBodyLimit::max(MessagePayload::BODY_LIMIT))),
+        )
+        .route("/search", get(search_messages))
         .route("/:id", patch(edit_message).delete(delete_message))
+        .route("/:id/thread", get(get_thread))
+        .route("/:id/history", get(get_history))
+        .route("/:id/ack", post(ack_message))
+        .route(
+            "/:id/reactions/:emoji",
+            put(add_reaction).delete(remove_reaction),
+        )
         .layer(
             RateLimitLayer::builder()
                 .with_user("messages")
@@ -178,3 +845,68 @@ pub fn create_router(context: Context) -> Router<Context> {
                 .build(context.clone()),
         )
 }
+
+pub fn create_search_router(context: Context) -> Router<Context> {
+    let auth_middleware = from_fn_with_state(context.clone(), auth::middleware);
+
+    Router::new()
+        .route("/search", get(search_all_messages))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("search")
+                .with_capacity(10)
+                .with_refill_rate(1)
+                .build(context),
+        )
+        .layer(auth_middleware)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_mentions;
+
+    fn usernames(content: &str) -> Vec<String> {
+        parse_mentions(content).0
+    }
+
+    #[test]
+    fn finds_mentions_at_word_boundaries() {
+        assert_eq!(usernames("@alice, hi @bobby!"), ["alice", "bobby"]);
+        assert_eq!(usernames("(@alice)"), ["alice"]);
+        assert!(usernames("mail alice@example.com").is_empty());
+        assert!(usernames("a_@alice").is_empty());
+    }
+
+    #[test]
+    fn finds_everyone() {
+        assert_eq!(parse_mentions("hey @everyone"), (Vec::new(), true));
+        assert_eq!(
+            parse_mentions("hey @everyones"),
+            (vec!["everyones".to_string()], false)
+        );
+        assert_eq!(parse_mentions("team@everyone"), (Vec::new(), false));
+    }
+
+    #[test]
+    fn enforces_username_length() {
+        assert!(usernames("@bob").is_empty());
+        assert_eq!(
+            usernames("@abcd @abcdefghijklmnop"),
+            ["abcd", "abcdefghijklmnop"]
+        );
+        assert!(usernames("@abcdefghijklmnopq").is_empty());
+    }
+
+    #[test]
+    fn removes_duplicates() {
+        assert_eq!(usernames("@alice @bobby @alice"), ["alice", "bobby"]);
+    }
+
+    #[test]
+    fn handles_non_ascii_neighbours() {
+        assert_eq!(usernames("é@alice"), ["alice"]);
+        assert_eq!(usernames("@aliceé"), ["alice"]);
+        assert_eq!(usernames("👋@alice…"), ["alice"]);
+        assert!(usernames("@é").is_empty());
+    }
+}
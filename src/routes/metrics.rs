@@ -0,0 +1,64 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, State},
+    routing::get,
+    Json, Router,
+};
+use axum_extra::{
+    headers::{authorization::Bearer, Authorization},
+    TypedHeader,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    rate_limit::{Component, Key, RateLimitLayer},
+    Context, Error,
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsResponse {
+    channels: usize,
+    receivers: usize,
+    typing_indicators: usize,
+}
+
+/// Requires the configured metrics token as a bearer token.
+pub async fn metrics(
+    State(context): State<Context>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+) -> Result<Json<MetricsResponse>, Error> {
+    // Hashes are compared so the time taken doesn't depend on how much of the token matches.
+    let is_authorized = match (context.metrics_token(), authorization) {
+        (Some(token), Some(TypedHeader(Authorization(bearer)))) => {
+            Sha256::digest(token) == Sha256::digest(bearer.token())
+        }
+        _ => false,
+    };
+
+    if !is_authorized {
+        return Err(Error::INVALID_TOKEN);
+    }
+
+    let subscriptions = context.subscriptions();
+
+    Ok(Json(MetricsResponse {
+        channels: subscriptions.len(),
+        receivers: subscriptions.receiver_count(),
+        typing_indicators: context.indicators().len(),
+    }))
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    Router::new().route("/", get(metrics)).layer(
+        RateLimitLayer::builder()
+            .with_fn(|ConnectInfo(addr): ConnectInfo<SocketAddr>| {
+                Key("metrics", Component::SocketAddr(addr))
+            })
+            .with_capacity(10)
+            .with_refill_rate(1)
+            .build(context),
+    )
+}
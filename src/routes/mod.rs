@@ -1,16 +1,33 @@
+pub mod attachments;
 pub mod auth;
+pub mod channels;
+pub mod files;
+pub mod gateway;
 pub mod groups;
 pub mod invites;
+pub mod members;
 pub mod messages;
+pub mod metrics;
+pub mod roles;
 pub mod users;
 
 use crate::Context;
 use axum::Router;
 
 pub fn create_router(context: Context) -> Router<Context> {
-    Router::new()
+    let mut router = Router::new()
         .nest("/auth", auth::create_router(context.clone()))
         .nest("/groups", groups::create_router(context.clone()))
         .nest("/users", users::create_router(context.clone()))
+        .nest("/messages", messages::create_search_router(context.clone()))
         .nest("/invites", invites::create_code_router(context.clone()))
+        .nest("/gateway", gateway::create_router(context.clone()))
+        .nest("/files", files::create_router());
+
+    // Metrics are only served when a token to read them is configured.
+    if context.metrics_token().is_some() {
+        router = router.nest("/metrics", metrics::create_router(context));
+    }
+
+    router
 }
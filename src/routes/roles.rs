@@ -0,0 +1,170 @@
+use axum::{
+    extract::{Path, State},
+    routing::{get, patch},
+    Extension, Json, Router,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    common::Garde,
+    models::{
+        group,
+        role::{self, NewRole, Permission},
+        Role, User,
+    },
+    rate_limit::RateLimitLayer,
+    Context, Error,
+};
+
+fn validate_permissions(permissions: &i64, _: &()) -> garde::Result {
+    if Permission::from_bits(*permissions).is_none() {
+        return Err(garde::Error::new("unknown permission bits"));
+    }
+
+    Ok(())
+}
+
+pub async fn get_roles(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+) -> Result<Json<Vec<Role>>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let roles = Role::fetch_all(group.id, context.pool()).await?;
+
+    Ok(Json(roles))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateRoleBody {
+    #[garde(length(min = 1, max = 32))]
+    name: String,
+    #[garde(custom(validate_permissions))]
+    permissions: i64,
+    #[garde(range(min = 0))]
+    position: i32,
+}
+
+pub async fn create_role(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(group_id): Path<Uuid>,
+    Garde(Json(body)): Garde<Json<CreateRoleBody>>,
+) -> Result<Json<Role>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let membership =
+        role::require_permission(user.id, &group, Permission::MANAGE_ROLES, context.pool()).await?;
+
+    let new_role = NewRole {
+        name: body.name,
+        permissions: Permission::from_bits_truncate(body.permissions),
+        position: body.position,
+    };
+
+    if !membership.is_owner && new_role.position >= membership.position {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    if !membership.can_grant(new_role.permissions) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    let role = Role::create(group.id, &new_role, context.pool()).await?;
+
+    Ok(Json(role))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRoleBody {
+    #[garde(length(min = 1, max = 32))]
+    name: Option<String>,
+    #[garde(inner(custom(validate_permissions)))]
+    permissions: Option<i64>,
+    #[garde(range(min = 0))]
+    position: Option<i32>,
+}
+
+pub async fn update_role(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, role_id)): Path<(Uuid, Uuid)>,
+    Garde(Json(body)): Garde<Json<UpdateRoleBody>>,
+) -> Result<Json<Role>, Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let membership =
+        role::require_permission(user.id, &group, Permission::MANAGE_ROLES, context.pool()).await?;
+
+    let role = Role::fetch(role_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ROLE)?;
+
+    if !membership.outranks(&role) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    let updated_role = NewRole {
+        name: body.name.unwrap_or_else(|| role.name.clone()),
+        permissions: body
+            .permissions
+            .map(Permission::from_bits_truncate)
+            .unwrap_or_else(|| role.permissions()),
+        position: body.position.unwrap_or(role.position),
+    };
+
+    if !membership.is_owner && updated_role.position >= membership.position {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    // Permissions the role already had may stay, even if the editor lacks them.
+    let granted = updated_role.permissions.difference(role.permissions());
+    if !membership.can_grant(granted) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    let role = Role::update(role.id, &updated_role, context.pool()).await?;
+
+    Ok(Json(role))
+}
+
+pub async fn delete_role(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path((group_id, role_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), Error> {
+    let group = group::fetch_with_membership_check(user.id, group_id, context.pool()).await?;
+    let membership =
+        role::require_permission(user.id, &group, Permission::MANAGE_ROLES, context.pool()).await?;
+
+    let role = Role::fetch(role_id, group.id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_ROLE)?;
+
+    if role.is_default {
+        return Err(Error::DEFAULT_ROLE);
+    }
+
+    if !membership.outranks(&role) {
+        return Err(Error::INSUFFICIENT_PERMISSIONS);
+    }
+
+    role.delete(context.pool()).await?;
+
+    Ok(())
+}
+
+pub fn create_router(context: Context) -> Router<Context> {
+    Router::new()
+        .route("/", get(get_roles).post(create_role))
+        .route("/:role_id", patch(update_role).delete(delete_role))
+        .layer(
+            RateLimitLayer::builder()
+                .with_user("roles")
+                .with_capacity(10)
+                .with_refill_rate(1)
+                .build(context),
+        )
+}
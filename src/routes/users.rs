@@ -1,14 +1,27 @@
 use axum::{
     extract::{Path, State},
     middleware::from_fn_with_state,
-    routing::get,
-    Json, Router,
+    response::{sse::Event, Sse},
+    routing::{delete, get, patch, post},
+    Extension, Json, Router,
 };
+use axum_extra::extract::CookieJar;
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use tokio::task;
+use tokio_stream::Stream;
 use uuid::Uuid;
 
-use crate::{models::User, rate_limit::RateLimitLayer, Context, Error};
+use crate::{
+    common::{sse_from_stream, Garde, LastEventId, Subscription},
+    event::{self, DeleteGroupEvent, RemovalReason},
+    models::{DirectMessage, Group, Session, User},
+    rate_limit::RateLimitLayer,
+    Context, Error,
+};
 
-use super::auth;
+use super::{auth, members};
 
 pub async fn get_user(
     State(ctx): State<Context>,
@@ -21,11 +34,155 @@ pub async fn get_user(
     Ok(Json(user))
 }
 
+pub async fn updates(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Extension(session): Extension<Session>,
+    LastEventId(last_event_id): LastEventId,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+    let groups = Group::fetch_all(user.id, context.pool()).await?;
+    let events = context
+        .subscriptions()
+        .subscribe_user(user.id, &groups, last_event_id);
+
+    Ok(sse_from_stream(
+        events,
+        auth::session_revoked(context, session, user),
+    ))
+}
+
+pub async fn get_direct_messages(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Vec<DirectMessage>>, Error> {
+    let direct_messages = DirectMessage::fetch_all(user.id, context.pool()).await?;
+
+    Ok(Json(direct_messages))
+}
+
+pub async fn create_direct_message(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DirectMessage>, Error> {
+    if id == user.id {
+        return Err(Error::CANNOT_MESSAGE_SELF);
+    }
+
+    let recipient = User::fetch(id, context.pool())
+        .await?
+        .ok_or(Error::UNKNOWN_USER)?;
+
+    let (direct_message, created) =
+        DirectMessage::find_or_create(user.id, recipient, context.pool()).await?;
+
+    if created {
+        for user_id in [user.id, direct_message.recipient.id] {
+            context.subscriptions().send(
+                &event::Event::JoinGroup(direct_message.group.clone()),
+                &Subscription::User(user_id),
+            );
+        }
+    }
+
+    Ok(Json(direct_message))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePasswordBody {
+    #[garde(length(min = 8))]
+    old_password: String,
+    #[garde(length(min = 8), custom(auth::validate_password_strength))]
+    new_password: String,
+}
+
+pub async fn update_password(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    Extension(session): Extension<Session>,
+    jar: CookieJar,
+    Garde(Json(body)): Garde<Json<UpdatePasswordBody>>,
+) -> Result<(CookieJar, Json<User>), Error> {
+    let old_password = body.old_password;
+    let password_hash = user.password_hash.clone();
+    let is_valid =
+        task::spawn_blocking(move || auth::verify_password(&old_password, &password_hash))
+            .await
+            .expect("failed to join blocking verify task")?;
+
+    if !is_valid {
+        return Err(Error::INVALID_CREDENTIALS);
+    }
+
+    let password_hash = task::spawn_blocking(|| auth::hash_password(body.new_password))
+        .await
+        .expect("failed to join blocking hash task")?;
+
+    let user = User::update_password(user.id, password_hash, session.id, context.pool()).await?;
+    let jar = auth::reissue_token(jar, &user, &session, context.keys().private_key())?;
+
+    Ok((jar, Json(user)))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct DeleteUserBody {
+    #[garde(length(min = 8))]
+    password: String,
+}
+
+pub async fn delete_user(
+    State(context): State<Context>,
+    Extension(user): Extension<User>,
+    jar: CookieJar,
+    Garde(Json(body)): Garde<Json<DeleteUserBody>>,
+) -> Result<CookieJar, Error> {
+    let password_hash = user.password_hash.clone();
+    let is_valid =
+        task::spawn_blocking(move || auth::verify_password(&body.password, &password_hash))
+            .await
+            .expect("failed to join blocking verify task")?;
+
+    if !is_valid {
+        return Err(Error::INVALID_CREDENTIALS);
+    }
+
+    let deleted = User::delete(user.id, context.pool()).await?;
+    for group_id in deleted.deleted_groups {
+        context.subscriptions().send(
+            &event::Event::DeleteGroup(DeleteGroupEvent { group_id }),
+            &Subscription::Group(group_id),
+        );
+    }
+
+    for group in deleted.transferred_groups {
+        context.subscriptions().send(
+            &event::Event::UpdateGroup(group.clone()),
+            &Subscription::Group(group.id),
+        );
+    }
+
+    for group_id in deleted.left_groups {
+        if let Err(error) =
+            members::notify_removal(&context, group_id, user.id, RemovalReason::Leave).await
+        {
+            tracing::warn!("failed to notify group {group_id} of deleted user: {error:?}");
+        }
+    }
+
+    Ok(auth::remove_session_cookies(jar))
+}
+
 pub fn create_router(context: Context) -> Router<Context> {
     let auth_middleware = from_fn_with_state(context.clone(), auth::middleware);
 
     Router::new()
+        .route("/@me", delete(delete_user))
+        .route("/@me/password", patch(update_password))
+        .route("/@me/updates", get(updates))
+        .route("/@me/dms", get(get_direct_messages))
         .route("/:id", get(get_user))
+        .route("/:id/dm", post(create_direct_message))
         .layer(
             RateLimitLayer::builder()
                 .with_user("users")